@@ -0,0 +1,160 @@
+//! Writes the synthetic quarantine files used by the golden round-trip tests.
+//!
+//! Every file wraps the same benign payload (no real malware, no EICAR string,
+//! so scanners leave the repository alone) with the vendor encoders.
+//!
+//! cargo run --example fixtures -- data/golden
+use crypto::{digest::Digest as _, sha1::Sha1};
+use md5::Digest;
+use std::{fs, path::Path};
+use unquarantine::{error::Error, metadata::Metadata, vendors};
+
+/// The benign payload every fixture restores to; 64 bytes, so the Blowfish
+/// based formats do not need padding
+const PAYLOAD: &[u8; 64] = b"MZ UNQUARANTINE-SYNTHETIC-TEST-FILE - harmless fixture payload!\n";
+
+type Encoder = fn(&[u8], &Metadata) -> unquarantine::Result<Vec<u8>>;
+
+fn main() -> Result<(), Error> {
+    let out = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "data/golden".to_string());
+    let out = Path::new(&out);
+    fs::create_dir_all(out)?;
+
+    let md5 = format!("{:x}", md5::Md5::digest(PAYLOAD));
+    let guid = "3f2504e0-4f89-11d3-9a0c-0305e82c3301";
+    let meta = Metadata {
+        original_path: Some("C:\\Users\\victim\\Downloads\\sample.exe".to_string()),
+        threat_name: Some("Unquarantine.Test.Fixture".to_string()),
+        quarantine_time: Some(1_640_995_200),
+        ..Default::default()
+    };
+
+    let fixtures: Vec<(String, Encoder)> = vec![
+        ("sample.v3b".into(), vendors::ahnlab::quarantine),
+        ("sample.ifc".into(), vendors::amiti::quarantine),
+        ("sample.eqf".into(), vendors::asquared::quarantine),
+        ("sample.chest".into(), vendors::avast::quarantine),
+        ("sample.qua".into(), vendors::avira::quarantine),
+        ("sample.qv".into(), vendors::baidu::quarantine),
+        ("sample.bdq".into(), vendors::bitdefender::quarantine),
+        ("sample.qrt".into(), vendors::cisco::amp_quarantine),
+        ("sample.cmc".into(), vendors::cmc::quarantine),
+        ("sample.vir".into(), vendors::esafe::quarantine),
+        ("sample.nqf".into(), vendors::eset::quarantine),
+        ("sample.tmp".into(), vendors::fprot::quarantine),
+        ("sample.q".into(), vendors::gdata::quarantine),
+        (format!("{}.QNT", md5), vendors::k7::quarantine),
+        ("sample.klq".into(), vendors::kaspersky::av_quarantine),
+        (
+            format!("{}.bin", md5),
+            vendors::kaspersky::system_watcher_quarantine,
+        ),
+        ("sample.lqf".into(), vendors::lumension::quarantine),
+        (
+            format!("{{{}}}-sample", guid),
+            vendors::microsoft::antimalware_quarantine,
+        ),
+        ("sample.defender".into(), vendors::microsoft::pc_quarantine),
+        (guid.to_string(), vendors::panda::quarantine),
+        ("sample.mal".into(), vendors::sentinelone::quarantine),
+        ("sample.qbd".into(), vendors::symantec::qbd_quarantine),
+        (
+            format!("{{{}}}", guid),
+            vendors::symantec::cc_sub_sdk_quarantine,
+        ),
+        ("submissions.idx".into(), vendors::symantec::idx_quarantine),
+        ("sample.vbn".into(), vendors::symantec::ep_quarantine),
+        ("quarantine.qtn".into(), vendors::symantec::qtn_quarantine),
+        ("sample.vsbx".into(), vendors::trendmicro::quarantine),
+        (format!("{{{}}}_ENC2", guid), vendors::vipre::quarantine),
+        (md5.clone(), vendors::quickheal::quarantine),
+    ];
+    for (name, encoder) in fixtures {
+        fs::write(out.join(name), encoder(PAYLOAD, &meta)?)?;
+    }
+    fs::write(
+        out.join("sample.quar"),
+        vendors::malwarebytes::quarantine(PAYLOAD, &meta)?,
+    )?;
+    fs::write(
+        out.join("sample.data"),
+        vendors::malwarebytes::data_quarantine(&[("id", "sample")])?,
+    )?;
+    fs::write(
+        out.join("sample.sdb"),
+        vendors::others::data_quarantine(PAYLOAD, 0xED)?,
+    )?;
+    fs::write(
+        out.join(format!("{}.dat", guid)),
+        vendors::others::zip_quarantine(PAYLOAD, Some(b"infected"))?,
+    )?;
+    fs::write(
+        out.join("spybot-1.zip"),
+        vendors::others::zip_quarantine(PAYLOAD, Some(b"recovery"))?,
+    )?;
+
+    // Defender MAC files are recognised by their 'un' magic, i.e. a zip payload
+    let mut zipped = b"PK".to_vec();
+    zipped.extend(&PAYLOAD[2..]);
+    fs::write(
+        out.join(format!("{}-mac", guid)),
+        vendors::microsoft::mac_quarantine(&zipped, &meta)?,
+    )?;
+
+    // BullGuard files share the G-Data magic but not its structure
+    let mut bullguard = vec![0xCA, 0xFE, 0xBA, 0xBE];
+    bullguard.extend(&PAYLOAD[4..]);
+    fs::write(out.join("bullguard.q"), bullguard)?;
+
+    let clamtk = out.join("clamtk");
+    fs::create_dir_all(clamtk.join("viruses"))?;
+    fs::create_dir_all(clamtk.join(vendors::clamav::HISTORY_DIR))?;
+    fs::write(
+        clamtk.join("viruses").join("sample.exe.VIRUS"),
+        vendors::clamav::unquarantine(PAYLOAD)?.remove(0),
+    )?;
+    fs::write(
+        clamtk.join(vendors::clamav::RESTORE_FILE),
+        vendors::clamav::clamtk_restore_entry(PAYLOAD, &meta),
+    )?;
+    fs::write(
+        clamtk
+            .join(vendors::clamav::HISTORY_DIR)
+            .join("Jan-01-2022.log"),
+        vendors::clamav::clamtk_history_entry(&meta),
+    )?;
+
+    // mdatp keeps the Windows layout under /var/opt/microsoft/mdatp/quarantine,
+    // ResourceData blobs named after their SHA1 and the Entries records
+    let mut sha1 = [0u8; 20];
+    let mut hasher = Sha1::new();
+    hasher.input(PAYLOAD);
+    hasher.result(&mut sha1);
+    let sha1_hex: String = sha1.iter().map(|b| format!("{:02X}", b)).collect();
+    let mdatp = out.join("mdatp").join("quarantine");
+    let resource = mdatp.join("ResourceData").join(&sha1_hex[..2]);
+    fs::create_dir_all(&resource)?;
+    fs::write(
+        resource.join(&sha1_hex),
+        vendors::microsoft::pc_quarantine(PAYLOAD, &meta)?,
+    )?;
+    let entries = mdatp.join("Entries");
+    fs::create_dir_all(&entries)?;
+    fs::write(
+        entries.join(format!("{{{}}}", guid.to_ascii_uppercase())),
+        vendors::microsoft::entries_quarantine(&sha1, &meta)?,
+    )?;
+
+    // Zemana <hash> files are told from QuickHeal ones by the quarantine.db
+    // next to them; the database itself is not read
+    let zemana = out.join("zemana");
+    fs::create_dir_all(&zemana)?;
+    fs::write(
+        zemana.join(&md5),
+        vendors::zemana::quarantine(PAYLOAD, &meta)?,
+    )?;
+    fs::write(zemana.join("quarantine.db"), b"")?;
+    Ok(())
+}
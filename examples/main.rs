@@ -1,6 +1,6 @@
 use clap::Parser;
 use std::{fs::OpenOptions, io::Write};
-use unquarantine::{error::Error, UnQuarantine};
+use unquarantine::{custom::CustomFormats, error::Error, Confidence, UnQuarantine};
 
 #[derive(Parser, Debug)]
 #[clap(author, version, about, long_about = None)]
@@ -9,11 +9,24 @@ struct CliOpts {
     input_file: String,
     #[clap(short = 'o', long, value_name = "FILE")]
     output_file: String,
+    /// TOML/YAML file with additional quarantine format definitions
+    #[clap(short = 'f', long, value_name = "FILE")]
+    formats: Option<String>,
+    /// Print every check evaluated to pick the vendor
+    #[clap(long)]
+    explain: bool,
 }
 
 fn main() -> Result<(), Error> {
     let cli = CliOpts::parse();
-    let result = UnQuarantine::from_file(&cli.input_file);
+    let formats = match &cli.formats {
+        Some(f) => CustomFormats::from_file(f)?,
+        None => CustomFormats::default(),
+    };
+    let (result, trace) = UnQuarantine::explain(&cli.input_file, Some(&formats));
+    if cli.explain {
+        print!("{}", trace);
+    }
     match result {
         Ok(res) => {
             let buff = res.get_unquarantined_buffer();
@@ -25,6 +38,9 @@ fn main() -> Result<(), Error> {
                 output_file.write_all(&buff[i])?;
             }
             println!("unquarantied for: {}", res.get_vendor());
+            if res.get_confidence() == Confidence::Low {
+                println!("low confidence: only a weak hint matched this vendor");
+            }
         }
         Err(e) => println!("Error: {:?}", e),
     }
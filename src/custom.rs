@@ -0,0 +1,384 @@
+//! Declarative quarantine formats loaded at runtime.
+//!
+//! Many products use trivially simple schemes (skip a header, then XOR with a
+//! constant or RC4 with a fixed key). Such formats can be described in a TOML
+//! or YAML file instead of being compiled into the crate:
+//!
+//! ```toml
+//! [[format]]
+//! name = "K7 QNT files (custom)"
+//! extension = "qnt"
+//! header = { offset = 0x178, length_field = { at = 0x128, size = 4 } }
+//! transform = [{ op = "xor", key = 0xFF }]
+//! ```
+//!
+//! Byte strings (`magic` and transform keys) are either a single byte
+//! (`0xAA`), plain text (`"AntiVir Qua"`), hex (`"hex:CAFEBABE"`) or the MD5
+//! digest of a text (`"md5:secret"`).
+use crate::{
+    error::Error,
+    utils::{bytearray_xor, file_name, rc4_decrypt, rc4_ksa},
+    vendors::others,
+    Result,
+};
+use md5::Digest;
+use regex::Regex;
+use serde::Deserialize;
+use std::{ffi::OsStr, path::Path};
+
+/// A byte string as written in a format definition
+#[derive(Clone, Debug, Deserialize)]
+#[serde(untagged)]
+pub enum ByteString {
+    /// A single byte, e.g. `key = 0xAA`
+    Byte(u8),
+    /// `hex:<hex digits>`, `md5:<text>` or plain text
+    Text(String),
+}
+
+impl ByteString {
+    /// Resolve the definition into the actual bytes
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        match self {
+            ByteString::Byte(b) => Ok(vec![*b]),
+            ByteString::Text(s) => {
+                if let Some(hex) = s.strip_prefix("hex:") {
+                    let hex: String = hex.chars().filter(|c| !c.is_whitespace()).collect();
+                    hex.as_bytes()
+                        .chunks(2)
+                        .map(|pair| {
+                            std::str::from_utf8(pair)
+                                .ok()
+                                .filter(|pair| pair.len() == 2)
+                                .and_then(|pair| u8::from_str_radix(pair, 16).ok())
+                                .ok_or_else(|| {
+                                    Error::CustomFormatError(format!("invalid hex: {}", s))
+                                })
+                        })
+                        .collect()
+                } else if let Some(text) = s.strip_prefix("md5:") {
+                    let mut hasher = md5::Md5::new();
+                    hasher.update(text.as_bytes());
+                    Ok(hasher.finalize().to_vec())
+                } else {
+                    Ok(s.as_bytes().to_vec())
+                }
+            }
+        }
+    }
+}
+
+/// Magic bytes expected at a given offset
+#[derive(Clone, Debug, Deserialize)]
+pub struct Magic {
+    #[serde(default)]
+    pub offset: usize,
+    pub bytes: ByteString,
+}
+
+/// A little endian integer stored in the quarantine header
+#[derive(Clone, Debug, Deserialize)]
+pub struct Field {
+    /// Offset of the field in the file
+    pub at: usize,
+    /// Width of the field in bytes: 1, 2, 4 or 8
+    #[serde(default = "default_field_size")]
+    pub size: usize,
+    /// Value added to the field once read
+    #[serde(default)]
+    pub add: i64,
+}
+
+fn default_field_size() -> usize {
+    4
+}
+
+impl Field {
+    fn read(&self, data: &[u8]) -> Result<usize> {
+        if ![1, 2, 4, 8].contains(&self.size) {
+            return Err(Error::CustomFormatError(format!(
+                "unsupported field size: {}",
+                self.size
+            )));
+        }
+        let out_of_range = |value: u64| Error::LengthOutOfRange {
+            field: "custom field",
+            offset: self.at,
+            value,
+            file_len: data.len(),
+        };
+        let end = self
+            .at
+            .checked_add(self.size)
+            .ok_or_else(|| out_of_range(self.at as u64))?;
+        let raw = data.get(self.at..end).ok_or_else(|| {
+            Error::CustomFormatError(format!("field at {:#x} is past the end of file", self.at))
+        })?;
+        let mut buf = [0u8; 8];
+        buf[..self.size].copy_from_slice(raw);
+        let raw = u64::from_le_bytes(buf);
+        let value = i64::try_from(raw)
+            .ok()
+            .and_then(|v| v.checked_add(self.add))
+            .ok_or_else(|| out_of_range(raw))?;
+        if value < 0 {
+            return Err(Error::CustomFormatError(format!(
+                "field at {:#x} is negative",
+                self.at
+            )));
+        }
+        Ok(value as usize)
+    }
+}
+
+/// Where the payload lives in the file
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct Header {
+    /// Fixed payload offset, used when `offset_field` is absent
+    #[serde(default)]
+    pub offset: usize,
+    /// Payload offset read from the file
+    pub offset_field: Option<Field>,
+    /// Payload length read from the file; the rest of the file when absent
+    pub length_field: Option<Field>,
+}
+
+/// A single step of the decoding pipeline
+#[derive(Clone, Debug, Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+pub enum Transform {
+    /// XOR with a (repeating) key
+    Xor { key: ByteString },
+    /// Add a constant to every byte (wrapping)
+    Add { value: u8 },
+    /// RC4 with a fixed key
+    Rc4 { key: ByteString },
+    /// Raw deflate, or zlib when `zlib = true`
+    Inflate {
+        #[serde(default)]
+        zlib: bool,
+    },
+    /// Base64 decode
+    Base64,
+    /// Extract every member of a zip archive
+    Zip { password: Option<String> },
+}
+
+impl Transform {
+    fn apply(&self, data: Vec<u8>) -> Result<Vec<Vec<u8>>> {
+        match self {
+            Transform::Xor { key } => {
+                let key = key.to_bytes()?;
+                if key.len() == 1 {
+                    return Ok(vec![bytearray_xor(data, key[0])]);
+                }
+                if key.is_empty() {
+                    return Err(Error::CustomFormatError("empty xor key".to_string()));
+                }
+                Ok(vec![data
+                    .iter()
+                    .enumerate()
+                    .map(|(i, b)| b ^ key[i % key.len()])
+                    .collect()])
+            }
+            Transform::Add { value } => {
+                Ok(vec![data.iter().map(|b| b.wrapping_add(*value)).collect()])
+            }
+            Transform::Rc4 { key } => {
+                let key = key.to_bytes()?;
+                if key.is_empty() {
+                    return Err(Error::CustomFormatError("empty rc4 key".to_string()));
+                }
+                let mut data = data;
+                Ok(vec![rc4_decrypt(&mut rc4_ksa(&key), &mut data)])
+            }
+            Transform::Inflate { zlib } => {
+                let res = if *zlib {
+                    inflate::inflate_bytes_zlib(&data)
+                } else {
+                    inflate::inflate_bytes(&data)
+                };
+                Ok(vec![res.map_err(Error::InflateError)?])
+            }
+            Transform::Base64 => Ok(vec![base64::decode(&data)?]),
+            Transform::Zip { password } => {
+                others::zip_unquarantine(&data, password.as_ref().map(|p| p.as_bytes()))
+            }
+        }
+    }
+}
+
+/// One user supplied quarantine format
+#[derive(Clone, Debug, Deserialize)]
+pub struct CustomFormat {
+    /// Vendor string reported for files of this format
+    pub name: String,
+    /// Magic bytes that must be present
+    pub magic: Option<Magic>,
+    /// File extension (case insensitive) that must match
+    pub extension: Option<String>,
+    /// Regex that the file name (the last path component) must match
+    pub filename: Option<String>,
+    /// Location of the payload
+    #[serde(default)]
+    pub header: Header,
+    /// Decoding pipeline, applied in order
+    #[serde(default)]
+    pub transform: Vec<Transform>,
+    #[serde(skip)]
+    filename_regex: Option<Regex>,
+}
+
+impl CustomFormat {
+    /// Check whether the given file belongs to this format.
+    /// Every condition given in the definition has to hold.
+    pub fn matches(&self, qf: &str, data: &[u8]) -> Result<bool> {
+        if let Some(magic) = &self.magic {
+            let bytes = magic.bytes.to_bytes()?;
+            let found = magic
+                .offset
+                .checked_add(bytes.len())
+                .and_then(|end| data.get(magic.offset..end));
+            if found != Some(&bytes[..]) {
+                return Ok(false);
+            }
+        }
+        // quarantined paths may come from another platform, so only the
+        // last component is looked at, the same way as the built in checks
+        let name = file_name(qf);
+        if let Some(extension) = &self.extension {
+            let file_extension = name
+                .rsplit_once('.')
+                .filter(|(stem, _)| !stem.is_empty())
+                .map(|(_, ext)| ext)
+                .unwrap_or_default();
+            if !file_extension.eq_ignore_ascii_case(extension.trim_start_matches('.')) {
+                return Ok(false);
+            }
+        }
+        if let Some(re) = &self.filename_regex {
+            if !re.is_match(name) {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    /// Run the quarantined data through the header and transform pipeline
+    pub fn unquarantine(&self, data: &[u8]) -> Result<Vec<Vec<u8>>> {
+        let start = match &self.header.offset_field {
+            Some(field) => field.read(data)?,
+            None => self.header.offset,
+        };
+        let end = match &self.header.length_field {
+            Some(field) => {
+                let len = field.read(data)?;
+                start.checked_add(len).ok_or(Error::LengthOutOfRange {
+                    field: "payload length",
+                    offset: field.at,
+                    value: len as u64,
+                    file_len: data.len(),
+                })?
+            }
+            None => data.len(),
+        };
+        if start > end || end > data.len() {
+            return Err(Error::CustomFormatError(format!(
+                "{}: payload {:#x}..{:#x} is out of range",
+                self.name, start, end
+            )));
+        }
+        let mut buffers = vec![data[start..end].to_vec()];
+        for transform in &self.transform {
+            let mut next = vec![];
+            for buffer in buffers {
+                next.extend(transform.apply(buffer)?);
+            }
+            buffers = next;
+        }
+        Ok(buffers)
+    }
+
+    fn compile(mut self) -> Result<Self> {
+        if self.magic.is_none() && self.extension.is_none() && self.filename.is_none() {
+            return Err(Error::CustomFormatError(format!(
+                "{}: at least one of magic, extension or filename is required",
+                self.name
+            )));
+        }
+        if let Some(filename) = &self.filename {
+            self.filename_regex = Some(Regex::new(filename)?);
+        }
+        Ok(self)
+    }
+}
+
+#[derive(Deserialize)]
+struct FormatFile {
+    #[serde(default)]
+    format: Vec<CustomFormat>,
+}
+
+/// A set of user supplied quarantine formats
+#[derive(Clone, Debug, Default)]
+pub struct CustomFormats {
+    formats: Vec<CustomFormat>,
+}
+
+impl CustomFormats {
+    pub fn from_file<P: AsRef<Path>>(file: P) -> Result<Self> {
+        //! Load format definitions from a `.toml`, `.yaml` or `.yml` file
+        //!
+        //! ## Example Usage
+        //! ```rust
+        //! use unquarantine::custom::CustomFormats;
+        //!
+        //! let formats = CustomFormats::from_file("examples/formats.toml");
+        //! assert!(formats.is_ok());
+        //! ```
+        let file = file.as_ref();
+        let text = String::from_utf8(crate::utils::read_file(file)?)?;
+        match file.extension().and_then(OsStr::to_str) {
+            Some(ext) if ext.eq_ignore_ascii_case("yaml") || ext.eq_ignore_ascii_case("yml") => {
+                Self::from_yaml(&text)
+            }
+            _ => Self::from_toml(&text),
+        }
+    }
+
+    /// Load format definitions from a TOML document
+    pub fn from_toml(text: &str) -> Result<Self> {
+        let file: FormatFile = toml::from_str(text)?;
+        Self::new(file.format)
+    }
+
+    /// Load format definitions from a YAML document
+    pub fn from_yaml(text: &str) -> Result<Self> {
+        let file: FormatFile = serde_yaml::from_str(text)?;
+        Self::new(file.format)
+    }
+
+    fn new(formats: Vec<CustomFormat>) -> Result<Self> {
+        Ok(Self {
+            formats: formats
+                .into_iter()
+                .map(CustomFormat::compile)
+                .collect::<Result<_>>()?,
+        })
+    }
+
+    /// The first format the given file matches, if any
+    pub fn find(&self, qf: &str, data: &[u8]) -> Result<Option<&CustomFormat>> {
+        for format in &self.formats {
+            if format.matches(qf, data)? {
+                return Ok(Some(format));
+            }
+        }
+        Ok(None)
+    }
+
+    /// All loaded formats
+    pub fn formats(&self) -> &[CustomFormat] {
+        &self.formats
+    }
+}
@@ -16,6 +16,8 @@ pub enum Error {
     Base64Error(#[from] base64::DecodeError),
     #[error("Zip error: {0}")]
     ZipError(#[from] zip::result::ZipError),
+    #[error("Zip error: {0}")]
+    ZipPasswordError(#[from] zip::result::InvalidPassword),
     #[error("Regex error: {0}")]
     RegexError(#[from] regex::Error),
     #[error("Inflate error: {0}")]
@@ -24,4 +26,54 @@ pub enum Error {
     CannotUnQuarantineFile(String),
     #[error("Ole file error: {0}")]
     OleError(#[from] ole::error::Error),
+    #[error("Custom format error: {0}")]
+    CustomFormatError(String),
+    #[error("Toml error: {0}")]
+    TomlError(#[from] toml::de::Error),
+    #[error("Yaml error: {0}")]
+    YamlError(#[from] serde_yaml::Error),
+    #[error("Json error: {0}")]
+    JsonError(#[from] serde_json::Error),
+    #[error("Xml error: {0}")]
+    XmlError(#[from] quick_xml::Error),
+    #[error("Plist error: {0}")]
+    PlistError(#[from] plist::Error),
+    #[error("{vendor}: bad magic, expected {expected:02X?} but found {found:02X?}")]
+    BadMagic {
+        vendor: &'static str,
+        expected: Vec<u8>,
+        found: Vec<u8>,
+    },
+    #[error(
+        "{field} at offset {offset:#x} is out of range: {value:#x} (file is {file_len:#x} bytes)"
+    )]
+    LengthOutOfRange {
+        field: &'static str,
+        offset: usize,
+        value: u64,
+        file_len: usize,
+    },
+    #[error("{vendor}: checksum mismatch, expected {expected} but computed {found}")]
+    ChecksumMismatch {
+        vendor: &'static str,
+        expected: String,
+        found: String,
+    },
+    #[error("{vendor}: unsupported version {version}")]
+    UnsupportedVersion { vendor: &'static str, version: u32 },
+    #[error("{vendor}: companion file {file} is missing")]
+    MissingCompanionFile { vendor: &'static str, file: String },
+    #[error("{file}: Cannot Un-quarantine file; no vendor matched{}", rejections(.attempts))]
+    NoMatchingVendor {
+        file: String,
+        /// Every vendor that was tried, with the reason it was rejected
+        attempts: Vec<(String, Error)>,
+    },
+}
+
+fn rejections(attempts: &[(String, Error)]) -> String {
+    attempts
+        .iter()
+        .map(|(vendor, reason)| format!("\n  {}: {}", vendor, reason))
+        .collect()
 }
@@ -6,17 +6,24 @@ extern crate lazy_static;
 
 mod patterns;
 mod utils;
-mod vendors;
 
 #[cfg(test)]
 mod tests;
 
+pub mod custom;
 pub mod error;
+pub mod metadata;
+pub mod trace;
+pub mod vendors;
 pub type Result<T> = std::result::Result<T, Error>;
 
+use crate::{
+    custom::CustomFormats,
+    error::Error,
+    metadata::{Metadata, Restored},
+    trace::{Probe, Trace},
+};
 use patterns::*;
-use std::{ffi::OsStr, path::Path};
-use crate::error::Error;
 
 /// This crate attempts to decrypt/restore/un-quarantine files from various AV / security products.
 /// When successful - it returns the Vendor String and the file buffer.
@@ -30,21 +37,23 @@ use crate::error::Error;
 /// * BitDefender (BDQ)
 /// * BullGuard (Q)
 /// * Cisco AMP
+/// * ClamTk / ClamAV on Linux (viruses/<file> + restore file)
 /// * CMC Antivirus (CMC)
 /// * ESafe (VIR)
 /// * ESET (NQF)
 /// * F-Prot (TMP) (Magic@0='KSS')
 /// * G-Data (Q) (Magic@0=0xCAFEBABE)
 /// * K7 Antivirus (<md5>.QNT)
-/// * Kaspersky (KLQ, System Watcher's <md5>.bin)
+/// * Kaspersky (KLQ, System Watcher's <md5>.bin; not the Endpoint Security SQLite quarantine.db)
 /// * Lavasoft AdAware (BDQ) /BitDefender files really/
 /// * Lumension LEMSS (lqf)
-/// * MalwareBytes Data files (DATA) - 2 versions
-/// * MalwareBytes Quarantine files (QUAR) - 2 versions
+/// * MalwareBytes Data files (DATA), JSON record fields kept as metadata of the matching QUAR file
+/// * MalwareBytes Quarantine files (QUAR)
 /// * McAfee Quarantine files (BUP) /full support for OLE format/
 /// * Microsoft Antimalware / Microsoft Security Essentials
 /// * Microsoft Defender PC (Magic@0=0B AD|D3 45) - D3 45 C5 99 metadata + 0B AD malicious content
 /// * Microsoft Defender Mac (Magic@0=75 6E)
+/// * Microsoft Defender for Endpoint on Linux and macOS (quarantine/ResourceData, as Defender PC, metadata from quarantine/Entries)
 /// * Panda <GUID> Zip files
 /// * Sentinel One (MAL)
 /// * Spybot - Search & Destroy 2 'recovery'
@@ -60,12 +69,30 @@ use crate::error::Error;
 /// * QuickHeal <hash> files
 /// * Vipre (<GUID>_ENC2)
 /// * Zemana <hash> files+quarantine.db
+///
+/// Other simple formats can be described in a TOML/YAML file and loaded at runtime, see [`custom`].
 #[derive(Clone)]
 pub struct UnQuarantine<'a> {
     /// The detected Vendor of the quarantined file
     vendor: &'a str,
     /// The buffer to save as restored file
     unquarantined_buffer: Vec<Vec<u8>>,
+    /// What is known about each restored buffer, in the same order
+    metadata: Vec<Metadata>,
+    /// How strongly the file matched the vendor format
+    confidence: Confidence,
+    /// Vendors tried before the one that matched, with the reason each was rejected
+    rejected: Vec<(String, String)>,
+}
+
+/// How strongly a file matched the format it was restored with
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Confidence {
+    /// Only a weak hint matched (e.g. the extension), or a plain XOR
+    /// format was guessed because nothing else matched
+    Low,
+    /// The magic, the file name or the structure matched
+    High,
 }
 
 impl<'a> UnQuarantine<'a> {
@@ -79,279 +106,344 @@ impl<'a> UnQuarantine<'a> {
         //! let result = UnQuarantine::from_file("data/99E865BA2BBCED427E8CB4785CCE58DDCCCE8337");
         //! assert!(result.is_ok());
         //! ```
-        let file_extension = Path::new(qf)
-            .extension()
-            .and_then(OsStr::to_str)
-            .unwrap_or_default();
+        Self::dispatch(qf, None, &mut Trace::default())
+    }
+
+    pub fn from_file_with_formats(qf: &str, formats: &'a CustomFormats) -> Result<Self> {
+        //! Unquarantine a given quarantined file, trying the user supplied
+        //! formats first and the built-in vendors after that
+        //!
+        //! ## Example Usage
+        //! ```rust
+        //! use unquarantine::{custom::CustomFormats, UnQuarantine};
+        //!
+        //! let formats = CustomFormats::from_file("examples/formats.toml").unwrap();
+        //! let result = UnQuarantine::from_file_with_formats("data/99E865BA2BBCED427E8CB4785CCE58DDCCCE8337", &formats);
+        //! assert!(result.is_ok());
+        //! ```
+        Self::dispatch(qf, Some(formats), &mut Trace::default())
+    }
+
+    pub fn explain(qf: &str, formats: Option<&'a CustomFormats>) -> (Result<Self>, Trace) {
+        //! Unquarantine a given quarantined file and return, along with the
+        //! result, every check that was evaluated to pick the vendor
+        //!
+        //! ## Example Usage
+        //! ```rust
+        //! use unquarantine::UnQuarantine;
+        //!
+        //! let (result, trace) = UnQuarantine::explain("data/99E865BA2BBCED427E8CB4785CCE58DDCCCE8337", None);
+        //! assert!(result.is_ok());
+        //! assert!(!trace.steps().is_empty());
+        //! ```
+        let mut trace = Trace::default();
+        let result = Self::dispatch(qf, formats, &mut trace);
+        (result, trace)
+    }
+
+    fn dispatch(qf: &str, formats: Option<&'a CustomFormats>, trace: &mut Trace) -> Result<Self> {
         let data = utils::read_file(&qf)?;
+        if let Some(formats) = formats {
+            for format in formats.formats() {
+                let matched = format.matches(qf, &data)?;
+                if trace.check(format!("custom format '{}'", format.name), matched) {
+                    let res = format.unquarantine(&data);
+                    trace.attempt(&format.name, &res);
+                    return Ok(Self::new(
+                        &format.name,
+                        res?.into_iter().map(Restored::new).collect(),
+                    ));
+                }
+            }
+        }
+        let mut p = Probe::new(qf, data, trace);
 
         // Start of Checks
-        if file_extension == "v3b" || data[..16] == b"AhnLab Inc. 2006"[..] {
-            return Ok(Self {
-                vendor: "AhnLab V3B files",
-                unquarantined_buffer: vendors::ahnlab::unquarantine(&data)?,
-            });
-        }
-        if file_extension.eq_ignore_ascii_case("eqf") {
-            return Ok(Self {
-                vendor: "ASquared EQF Files",
-                unquarantined_buffer: vendors::asquared::unquarantine(&data)?,
-            });
-        }
-        if data[..8] == b"-chest- "[..] {
-            return Ok(Self {
-                vendor: "Avast/AVG chest files",
-                unquarantined_buffer: vendors::avast::unquarantine(&data)?,
-            });
-        }
-        if file_extension.eq_ignore_ascii_case("qua") || data[..11] == b"AntiVir Qua"[..] {
-            return Ok(Self {
-                vendor: "Avira QUA Files",
-                unquarantined_buffer: vendors::avira::unquarantine(&data)?,
-            });
-        }
-        if file_extension.eq_ignore_ascii_case("qv") {
-            return Ok(Self {
-                vendor: "Baidu QV Files",
-                unquarantined_buffer: vendors::baidu::unquarantine(&data)?,
-            });
-        }
-        if file_extension.eq_ignore_ascii_case("bdq") {
-            return Ok(Self {
-                vendor: "BitDefender/Lavasoft AdAware/Total Defence: BDQ Files",
-                unquarantined_buffer: vendors::bitdefender::unquarantine(&data)?,
-            });
-        }
-        if file_extension.eq_ignore_ascii_case("q") && data[..4] == vec![0xCA, 0xFE, 0xBA, 0xBE] {
-            let newdata = vendors::gdata::unquarantine(&data);
-            return match newdata {
-                Err(_) => Ok(Self {
-                    vendor: "BullGuard Q Files",
-                    unquarantined_buffer: vendors::bullguard::unquarantine(&data)?,
-                }),
-                Ok(s) => Ok(Self {
-                    vendor: "G-Data Q Files",
-                    unquarantined_buffer: s,
-                }),
-            };
-        }
-        if file_extension.to_lowercase().starts_with("qrt") {
-            return Ok(Self {
-                vendor: "Cisco AMP",
-                unquarantined_buffer: vendors::cisco::amp_unquarantine(&data)?,
-            });
-        }
-        if file_extension.eq_ignore_ascii_case("cmc")
-            && data[..23] == b"CMC Quarantined Malware"[..]
-        {
-            return Ok(Self {
-                vendor: "CMC Antivirus CMC Files",
-                unquarantined_buffer: vendors::cmc::unquarantine(&data)?,
-            });
-        }
-        if file_extension.eq_ignore_ascii_case("vir") {
-            return Ok(Self {
-                vendor: "ESafe VIR Files",
-                unquarantined_buffer: vendors::esafe::unquarantine(&data)?,
-            });
-        }
-        if file_extension.eq_ignore_ascii_case("ifc") {
-            return Ok(Self {
-                vendor: "Amiti IFC Files",
-                unquarantined_buffer: vendors::amiti::unquarantine(&data)?,
-            });
-        }
-        if file_extension.eq_ignore_ascii_case("nqf") {
-            return Ok(Self {
-                vendor: "ESET NQF Files",
-                unquarantined_buffer: vendors::eset::unquarantine(&data)?,
-            });
-        }
-        if file_extension.eq_ignore_ascii_case("tmp") || data[..3] == b"KSS"[..] {
-            return Ok(Self {
-                vendor: "F-Prot TMP Files",
-                unquarantined_buffer: vendors::fprot::unquarantine(&data)?,
-            });
-        }
-        if file_extension.eq_ignore_ascii_case("klq") || data[..4] == b"KLQB"[..] {
-            return Ok(Self {
-                vendor: "Kaspersky KLQ files",
-                unquarantined_buffer: vendors::kaspersky::av_unquarantine(&data)?,
-            });
-        }
-        if file_extension.eq_ignore_ascii_case("QNT") {
-            return Ok(Self {
-                vendor: "K7 QNT files",
-                unquarantined_buffer: vendors::k7::unquarantine(&data)?,
-            });
-        }
-        if file_extension.eq_ignore_ascii_case("bin") {
-            return Ok(Self {
-                vendor: "Kaspersky System Watcher files",
-                unquarantined_buffer: vendors::kaspersky::system_watcher_unquarantine(&data)?,
-            });
-        }
-        if file_extension.eq_ignore_ascii_case("lqf") {
-            return Ok(Self {
-                vendor: "Lumension LEMSS",
-                unquarantined_buffer: vendors::lumension::unquarantine(&data)?,
-            });
-        }
-        if file_extension.eq_ignore_ascii_case("quar")
-            || file_extension.eq_ignore_ascii_case("data")
-            || qf.to_lowercase().ends_with("data")
-        {
-            return Ok(Self {
-                vendor: "MalwareBytes DATA and QUAR Files",
-                unquarantined_buffer: vendors::malwarebytes::unquarantine(&data)?,
-            });
+        if p.ext("v3b") || p.magic(b"AhnLab Inc. 2006") {
+            return p.restore("AhnLab V3B files", vendors::ahnlab::unquarantine(&p.data));
+        }
+        if p.ext("eqf") {
+            return p.restore(
+                "ASquared EQF Files",
+                vendors::asquared::unquarantine(&p.data),
+            );
+        }
+        if p.magic(b"-chest- ") {
+            return p.restore(
+                "Avast/AVG chest files",
+                vendors::avast::unquarantine(&p.data),
+            );
+        }
+        if p.magic(b"AntiVir Qua") {
+            return p.restore("Avira QUA Files", vendors::avira::unquarantine(&p.data));
+        }
+        if p.ext("qua") {
+            p.candidate("Avira QUA Files", vendors::avira::unquarantine(&p.data));
+        }
+        if p.ext("qv") {
+            return p.restore("Baidu QV Files", vendors::baidu::unquarantine(&p.data));
+        }
+        if p.ext("bdq") {
+            return p.restore(
+                "BitDefender/Lavasoft AdAware/Total Defence: BDQ Files",
+                vendors::bitdefender::unquarantine(&p.data),
+            );
+        }
+        if p.ext("q") && p.magic(&[0xCA, 0xFE, 0xBA, 0xBE]) {
+            let gdata = vendors::gdata::is_gdata(&p.data);
+            if p.trace()
+                .check("BAADF00D body block after the header".to_string(), gdata)
+            {
+                return p.restore("G-Data Q Files", vendors::gdata::unquarantine(&p.data));
+            }
+            return p.restore(
+                "BullGuard Q Files",
+                vendors::bullguard::unquarantine(&p.data),
+            );
+        }
+        if p.ext_prefix("qrt") {
+            return p.restore("Cisco AMP", vendors::cisco::amp_unquarantine(&p.data));
+        }
+        if p.ext("cmc") && p.magic(b"CMC Quarantined Malware") {
+            return p.restore(
+                "CMC Antivirus CMC Files",
+                vendors::cmc::unquarantine(&p.data),
+            );
+        }
+        if p.ext("vir") {
+            return p.restore("ESafe VIR Files", vendors::esafe::unquarantine(&p.data));
+        }
+        if p.ext("ifc") {
+            return p.restore("Amiti IFC Files", vendors::amiti::unquarantine(&p.data));
+        }
+        if p.ext("nqf") {
+            return p.restore("ESET NQF Files", vendors::eset::unquarantine(&p.data));
+        }
+        if p.ext("tmp") || p.magic(b"KSS") {
+            return p.restore("F-Prot TMP Files", vendors::fprot::unquarantine(&p.data));
+        }
+        if p.ext("klq") || p.magic(b"KLQB") {
+            return p.restore(
+                "Kaspersky KLQ files",
+                vendors::kaspersky::av_unquarantine(&p.data),
+            );
+        }
+        if p.ext("QNT") {
+            return p.restore_meta(
+                "K7 QNT files",
+                vendors::k7::restore(qf, &p.data).map(|r| vec![r]),
+            );
+        }
+        if p.name_matches(&MD5_BIN_PATTERN) {
+            let res = vendors::kaspersky::system_watcher_restore(qf, &p.data);
+            if res.is_ok() {
+                return p.restore_meta("Kaspersky System Watcher files", res.map(|r| vec![r]));
+            }
+            p.trace().attempt("Kaspersky System Watcher files", &res);
         }
-        if file_extension.eq_ignore_ascii_case("bup")
-            && data[..8] == vec![0xD0, 0xCF, 0x11, 0xE0, 0xA1, 0xB1, 0x1A, 0xE1]
-        {
-            return Ok(Self {
-                vendor: "McAfee BUP Files",
-                unquarantined_buffer: vendors::mcafee::unquarantine(qf)?,
-            });
-        }
-        if MSE_PATTERN.is_match(qf) {
-            return Ok(Self {
-                vendor: "Microsoft Antimalware / Microsoft Security Essentials",
-                unquarantined_buffer: vendors::microsoft::antimalware_unquarantine(&data)?,
-            });
-        }
-        if DEFAULT_FILE_PATTERN.is_match(qf) && data[..2] == vec![0x75, 0x6E] {
-            return Ok(Self {
-                vendor: "Microsoft Defender MAC",
-                unquarantined_buffer: vendors::microsoft::mac_unquarantine(&data)?,
-            });
-        }
-        if data[..2] == vec![0xD3, 0x45] || data[..2] == vec![0x0B, 0xAD] {
-            return Ok(Self {
-                vendor: "Microsoft Defender PC - partially supported (D3 45 C5 99 header)",
-                unquarantined_buffer: vendors::microsoft::pc_unquarantine(&data)?,
-            });
-        }
-        if DEFAULT_FILE_PATTERN.is_match(qf) && data[..2] == b"PK"[..] {
-            return Ok(Self {
-                vendor: "Panda <GUID> Zip Files",
-                unquarantined_buffer: vendors::panda::unquarantine(&data)?,
-            });
-        }
-        if file_extension.eq_ignore_ascii_case("mal") {
-            return Ok(Self {
-                vendor: "Sentinel One MAL files",
-                unquarantined_buffer: vendors::sentinelone::unquarantine(&data)?,
-            });
-        }
-        if NUM_PATTERN.is_match(qf) && data[..2] == b"PK"[..] {
-            if GUID_DAT_PATTERN.is_match(qf) && data[..2] == b"PK"[..] {
-                return Ok(Self {
-                    vendor: "Total AV {GUID}.dat",
-                    unquarantined_buffer: vendors::others::zip_unquarantine(&data, Some(b"infected"))?,
-                });
+        if p.parent_is("viruses") {
+            // ClamTk keeps plain copies, only its restore file tells them apart
+            let res = vendors::clamav::clamtk_restore(qf, &p.data);
+            if res.is_ok() {
+                return p.restore_meta("ClamTk quarantine", res.map(|r| vec![r]));
             }
-            return Ok(Self {
-                vendor: "Spybot - Search & Destroy 2 Zip Files",
-                unquarantined_buffer: vendors::others::zip_unquarantine(&data, Some(b"recovery"))?,
-            });
-        }
-        if file_extension.eq_ignore_ascii_case("sdb") {
-            return Ok(Self {
-                vendor: "SUPERAntiSpyware (SDB)",
-                unquarantined_buffer: vendors::others::data_unquarantine(&data, 0xED)?,
-            });
-        }
-        if file_extension.eq_ignore_ascii_case("qbd") || file_extension.eq_ignore_ascii_case("qbi")
+            p.trace().attempt("ClamTk quarantine", &res);
+            // copies moved there by clamscan --move leave no record at all
+            p.candidate("ClamAV quarantine", vendors::clamav::unquarantine(&p.data));
+        }
+        if p.ext("lqf") {
+            return p.restore("Lumension LEMSS", vendors::lumension::unquarantine(&p.data));
+        }
+        if p.ext("quar") || p.ext("data") || p.name_ends_with("data") {
+            return p.restore_meta(
+                "MalwareBytes DATA and QUAR Files",
+                vendors::malwarebytes::restore(qf, &p.data),
+            );
+        }
+        if p.ext("bup") && p.magic(&[0xD0, 0xCF, 0x11, 0xE0, 0xA1, 0xB1, 0x1A, 0xE1]) {
+            return p.restore_meta("McAfee BUP Files", vendors::mcafee::restore(qf));
+        }
+        if p.name_matches(&MSE_PATTERN) {
+            return p.restore(
+                "Microsoft Antimalware / Microsoft Security Essentials",
+                vendors::microsoft::antimalware_unquarantine(&p.data),
+            );
+        }
+        if p.name_matches(&DEFAULT_FILE_PATTERN) && p.magic(&[0x75, 0x6E]) {
+            return p.restore(
+                "Microsoft Defender MAC",
+                vendors::microsoft::mac_unquarantine(&p.data),
+            );
+        }
+        if p.in_dir("ResourceData")
+            && (p.in_dir("mdatp") || (p.in_dir("Microsoft") && p.in_dir("Defender")))
         {
-            return Ok(Self {
-                vendor: "Symantec QBD and QBI Files",
-                unquarantined_buffer: vendors::symantec::qbd_unquarantine(&data)?,
-            });
-        }
-        if GUID_PATTERN.is_match(qf) {
-            return Ok(Self {
-                vendor: "Symantec ccSubSDK {GUID} Files",
-                unquarantined_buffer: vendors::symantec::cc_sub_sdk_unquarantine(&data)?,
-            });
-        }
-        if qf.to_ascii_lowercase().ends_with("submissions.idx") {
-            return Ok(Self {
-                vendor: "Symantec ccSubSDK submissions.idx Files",
-                unquarantined_buffer: vendors::symantec::idx_unquarantine(&data)?,
-            });
-        }
-        if qf.eq_ignore_ascii_case("quarantine.qtn") && data[..2] == b"PK"[..] {
-            return Ok(Self {
-                vendor: "Symantec quarantine.qtn",
-                unquarantined_buffer: vendors::symantec::qtn_unquarantine(&data)?,
-            });
-        }
-        if file_extension.eq_ignore_ascii_case("vbn") {
-            return Ok(Self {
-                vendor: "Symantec VBN Files",
-                unquarantined_buffer: vendors::symantec::ep_unquarantine(&data)?,
-            });
-        }
-        if utils::unpack_i32(&data)? == 0x58425356 {
-            return Ok(Self {
-                vendor: "TrendMicro VSBX files",
-                unquarantined_buffer: vendors::trendmicro::unquarantine(&data)?,
-            });
-        }
-        if QDB_PATTERN.is_match(qf) {
-            if qf.eq_ignore_ascii_case("quarantine.db") {
-                return Ok(Self {
-                    vendor: "QuickHeal Files",
-                    unquarantined_buffer: vendors::quickheal::unquarantine(&data)?,
-                });
+            return p.restore_meta(
+                "Microsoft Defender for Endpoint (Linux/macOS)",
+                vendors::microsoft::resource_restore(qf, &p.data).map(|r| vec![r]),
+            );
+        }
+        if p.magic(&[0xD3, 0x45]) || p.magic(&[0x0B, 0xAD]) || p.in_dir("ResourceData") {
+            return p.restore_meta(
+                "Microsoft Windows Defender (PC)",
+                vendors::microsoft::resource_restore(qf, &p.data).map(|r| vec![r]),
+            );
+        }
+        if p.name_matches(&GUID_DAT_PATTERN) && p.magic(b"PK") {
+            return p.restore(
+                "Total AV {GUID}.dat",
+                vendors::others::zip_unquarantine(&p.data, Some(b"infected")),
+            );
+        }
+        if p.name_matches(&DEFAULT_FILE_PATTERN) && p.magic(b"PK") {
+            return p.restore(
+                "Panda <GUID> Zip Files",
+                vendors::panda::unquarantine(&p.data),
+            );
+        }
+        if p.ext("mal") {
+            return p.restore(
+                "Sentinel One MAL files",
+                vendors::sentinelone::unquarantine(&p.data),
+            );
+        }
+        if p.name_matches(&NUM_PATTERN) && p.magic(b"PK") {
+            return p.restore(
+                "Spybot - Search & Destroy 2 Zip Files",
+                vendors::others::zip_unquarantine(&p.data, Some(b"recovery")),
+            );
+        }
+        if p.ext("sdb") {
+            return p.restore(
+                "SUPERAntiSpyware (SDB)",
+                vendors::others::data_unquarantine(&p.data, 0xED),
+            );
+        }
+        if p.ext("qbd") || p.ext("qbi") {
+            return p.restore(
+                "Symantec QBD and QBI Files",
+                vendors::symantec::qbd_unquarantine(&p.data),
+            );
+        }
+        if p.name_matches(&VIPRE_PATTERN) {
+            return p.restore(
+                "Vipre <GUID>_ENC2 Files",
+                vendors::vipre::unquarantine(&p.data),
+            );
+        }
+        if p.name_matches(&GUID_PATTERN) {
+            return p.restore(
+                "Symantec ccSubSDK {GUID} Files",
+                vendors::symantec::cc_sub_sdk_unquarantine(&p.data),
+            );
+        }
+        if p.name_ends_with("submissions.idx") {
+            return p.restore(
+                "Symantec ccSubSDK submissions.idx Files",
+                vendors::symantec::idx_unquarantine(&p.data),
+            );
+        }
+        if p.name_is("quarantine.qtn") && p.magic(b"PK") {
+            return p.restore_meta(
+                "Symantec quarantine.qtn",
+                vendors::symantec::qtn_restore(&p.data),
+            );
+        }
+        if p.ext("vbn") {
+            return p.restore(
+                "Symantec VBN Files",
+                vendors::symantec::ep_unquarantine(&p.data),
+            );
+        }
+        if p.magic(&[0xA9, 0xAC, 0xBD, 0xA7]) {
+            return p.restore_meta(
+                "TrendMicro VSBX files",
+                vendors::trendmicro::restore(&p.data).map(|r| vec![r]),
+            );
+        }
+        if p.name_matches(&QDB_PATTERN) {
+            // Zemana keeps its quarantine.db next to the <hash> files
+            if p.sibling_exists("quarantine.db") {
+                return p.restore("Zemana Files", vendors::zemana::unquarantine(&p.data));
             }
-            return Ok(Self {
-                vendor: "Zemana Files",
-                unquarantined_buffer: vendors::zemana::unquarantine(&data)?,
-            });
+            return p.restore("QuickHeal Files", vendors::quickheal::unquarantine(&p.data));
         }
 
-        if let Ok(s) = vendors::kaspersky::av_unquarantine(&data) {
-            return Ok(Self {
-                vendor: "Kaspersky Antivirus",
-                unquarantined_buffer: s,
-            });
-        }
-        if let Ok(s) = vendors::trendmicro::unquarantine(&data) {
-            return Ok(Self {
-                vendor: "TrendMicro",
-                unquarantined_buffer: s,
-            });
-        }
-        if let Ok(s) = vendors::symantec::ep_unquarantine(&data) {
-            return Ok(Self {
-                vendor: "Symantec Endpoint",
-                unquarantined_buffer: s,
-            });
-        }
-        if let Ok(s) = vendors::microsoft::pc_unquarantine(&data) {
-            return Ok(Self {
-                vendor: "Microsoft Windows Defender (PC)",
-                unquarantined_buffer: s,
-            });
-        }
-        if let Ok(s) = vendors::vipre::unquarantine(&data) {
-            return Ok(Self {
-                vendor: "Vipre <GUID>_ENC2 Files",
-                unquarantined_buffer: s,
-            });
-        }
-        if let Ok(s) = vendors::others::xorff_unquarantine(&data) {
-            return Ok(Self {
-                vendor: "Generic xorff",
-                unquarantined_buffer: s,
-            });
+        // A format that only matched by extension beats a guess
+        if let Some(found) = p.take_candidate() {
+            return Ok(found);
         }
 
-        Err(Error::CannotUnQuarantineFile(qf.to_string()))
+        // Nothing matched by name or magic: try the formats that validate
+        // their own structure, keeping the reason each one was rejected.
+        // Plain XOR formats accept anything, so their results are a guess
+        // that carries the rejections; an empty file leaves nothing to guess.
+        type Fallback = (&'static str, fn(&[u8]) -> Result<Vec<Vec<u8>>>, bool);
+        let fallbacks: [Fallback; 6] = [
+            (
+                "Kaspersky Antivirus",
+                vendors::kaspersky::av_unquarantine,
+                false,
+            ),
+            ("TrendMicro", vendors::trendmicro::unquarantine, false),
+            (
+                "Symantec Endpoint",
+                vendors::symantec::ep_unquarantine,
+                false,
+            ),
+            (
+                "Microsoft Windows Defender (PC)",
+                vendors::microsoft::pc_unquarantine,
+                false,
+            ),
+            (
+                "Vipre <GUID>_ENC2 Files",
+                vendors::vipre::unquarantine,
+                true,
+            ),
+            ("Generic xorff", vendors::others::xorff_unquarantine, true),
+        ];
+        let mut attempts: Vec<(String, Error)> = vec![];
+        for (vendor, unquarantine, guess) in fallbacks {
+            if guess && p.data.is_empty() {
+                break;
+            }
+            let res = unquarantine(&p.data);
+            p.trace().attempt(vendor, &res);
+            match res {
+                Ok(s) => {
+                    let mut found = Self::new(vendor, s.into_iter().map(Restored::new).collect());
+                    if guess {
+                        found.confidence = Confidence::Low;
+                        found.rejected = attempts
+                            .iter()
+                            .map(|(vendor, e)| (vendor.clone(), e.to_string()))
+                            .collect();
+                    }
+                    return Ok(found);
+                }
+                Err(e) => attempts.push((vendor.to_string(), e)),
+            }
+        }
+
+        Err(Error::NoMatchingVendor {
+            file: qf.to_string(),
+            attempts,
+        })
+    }
+
+    fn new(vendor: &'a str, restored: Vec<Restored>) -> Self {
+        let (unquarantined_buffer, metadata) =
+            restored.into_iter().map(|r| (r.data, r.metadata)).unzip();
+        Self {
+            vendor,
+            unquarantined_buffer,
+            metadata,
+            confidence: Confidence::High,
+            rejected: vec![],
+        }
     }
-    
+
     pub fn get_vendor(&self) -> &str {
         //! Gets the Vendor String of the Quarantined File
         //!
@@ -383,4 +475,46 @@ impl<'a> UnQuarantine<'a> {
         //! ```
         self.unquarantined_buffer.to_owned()
     }
+
+    pub fn get_metadata(&self) -> &[Metadata] {
+        //! Gets the metadata (original path, threat name, quarantine time)
+        //! of each restored buffer, where the vendor format keeps it
+        //!
+        //! ## Example Usage
+        //! ```rust
+        //! use unquarantine::UnQuarantine;
+        //!
+        //! let result = UnQuarantine::from_file("data/99E865BA2BBCED427E8CB4785CCE58DDCCCE8337").unwrap();
+        //! assert_eq!(result.get_metadata().len(), result.get_unquarantined_buffer().len());
+        //! ```
+        &self.metadata
+    }
+
+    pub fn get_confidence(&self) -> Confidence {
+        //! Gets how strongly the file matched the detected vendor format;
+        //! matches on the extension alone are [`Confidence::Low`]
+        //!
+        //! ## Example Usage
+        //! ```rust
+        //! use unquarantine::{Confidence, UnQuarantine};
+        //!
+        //! let result = UnQuarantine::from_file("data/99E865BA2BBCED427E8CB4785CCE58DDCCCE8337").unwrap();
+        //! assert_eq!(result.get_confidence(), Confidence::High);
+        //! ```
+        self.confidence
+    }
+
+    pub fn get_rejected(&self) -> &[(String, String)] {
+        //! Gets the vendors that were tried and rejected before a
+        //! [`Confidence::Low`] guess was accepted, with the reason for each
+        //!
+        //! ## Example Usage
+        //! ```rust
+        //! use unquarantine::UnQuarantine;
+        //!
+        //! let result = UnQuarantine::from_file("data/99E865BA2BBCED427E8CB4785CCE58DDCCCE8337").unwrap();
+        //! assert!(result.get_rejected().is_empty());
+        //! ```
+        &self.rejected
+    }
 }
@@ -0,0 +1,60 @@
+use std::collections::BTreeMap;
+
+/// Information about a quarantined file, as kept by the security product
+/// next to (or inside) the quarantined data.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Metadata {
+    /// Path of the file before it was quarantined
+    pub original_path: Option<String>,
+    /// Detection / threat name reported by the product
+    pub threat_name: Option<String>,
+    /// When the file was quarantined, in seconds since the unix epoch
+    pub quarantine_time: Option<u64>,
+    /// Any other vendor specific fields
+    pub extra: BTreeMap<String, String>,
+}
+
+impl Metadata {
+    /// True when nothing is known about the file
+    pub fn is_empty(&self) -> bool {
+        self.original_path.is_none()
+            && self.threat_name.is_none()
+            && self.quarantine_time.is_none()
+            && self.extra.is_empty()
+    }
+
+    /// Fill in whatever is still unknown from `other`
+    pub fn merge(&mut self, other: &Metadata) {
+        if self.original_path.is_none() {
+            self.original_path = other.original_path.clone();
+        }
+        if self.threat_name.is_none() {
+            self.threat_name = other.threat_name.clone();
+        }
+        if self.quarantine_time.is_none() {
+            self.quarantine_time = other.quarantine_time;
+        }
+        for (k, v) in &other.extra {
+            self.extra.entry(k.clone()).or_insert_with(|| v.clone());
+        }
+    }
+}
+
+/// A restored file together with what is known about it
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Restored {
+    /// The original file content
+    pub data: Vec<u8>,
+    /// Metadata found in the quarantine file or its index
+    pub metadata: Metadata,
+}
+
+impl Restored {
+    /// A restored file without metadata
+    pub fn new(data: Vec<u8>) -> Self {
+        Self {
+            data,
+            metadata: Metadata::default(),
+        }
+    }
+}
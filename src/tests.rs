@@ -1,4 +1,29 @@
 use super::UnQuarantine;
+use crate::metadata::Metadata;
+use std::path::{Path, PathBuf};
+
+/// A scratch folder in the system temp folder, unique to the test process
+/// and `label`, removed with everything in it when dropped
+struct TempDir(PathBuf);
+
+impl TempDir {
+    fn new(label: &str) -> Self {
+        let dir =
+            std::env::temp_dir().join(format!("unquarantine-{}-{}", std::process::id(), label));
+        std::fs::create_dir_all(&dir).unwrap();
+        Self(dir)
+    }
+
+    fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
 
 #[test]
 fn test_unquarantine_result() {
@@ -26,4 +51,898 @@ fn test_mcafee() {
     assert_eq!(vendor, "McAfee BUP Files");
     let unquarantine_buffer = result.get_unquarantined_buffer();
     assert!(!unquarantine_buffer.is_empty());
-}
\ No newline at end of file
+}
+#[test]
+fn test_custom_formats() {
+    use crate::custom::CustomFormats;
+
+    let formats = CustomFormats::from_toml(
+        r#"
+        [[format]]
+        name = "Test QUA"
+        magic = { bytes = "hex:51554100" }
+        header = { offset_field = { at = 4, size = 2, add = 2 } }
+        transform = [{ op = "xor", key = 0xAA }, { op = "add", value = 1 }]
+        "#,
+    )
+    .unwrap();
+    let mut data = vec![0x51, 0x55, 0x41, 0x00, 0x06, 0x00, 0xFF, 0xFF];
+    data.extend(b"MZ".iter().map(|b| (b - 1) ^ 0xAA));
+    let format = formats.find("sample.qua", &data).unwrap().unwrap();
+    assert_eq!(format.name, "Test QUA");
+    assert_eq!(format.unquarantine(&data).unwrap(), vec![b"MZ".to_vec()]);
+    assert!(formats.find("sample.qua", b"MZ").unwrap().is_none());
+
+    // lengths that do not fit are errors, not overflows
+    let formats = CustomFormats::from_toml(
+        r#"
+        [[format]]
+        name = "Test Overflow"
+        magic = { bytes = "hex:51554100" }
+        header = { length_field = { at = 4, size = 8, add = 1 } }
+        "#,
+    )
+    .unwrap();
+    let mut data = vec![0x51, 0x55, 0x41, 0x00];
+    data.extend(i64::MAX.to_le_bytes());
+    let format = formats.find("sample.qua", &data).unwrap().unwrap();
+    assert!(matches!(
+        format.unquarantine(&data),
+        Err(crate::error::Error::LengthOutOfRange { .. })
+    ));
+    data[4..12].copy_from_slice(&u64::MAX.to_le_bytes());
+    assert!(matches!(
+        format.unquarantine(&data),
+        Err(crate::error::Error::LengthOutOfRange { .. })
+    ));
+
+    // extension and file name are taken from the last component, whichever
+    // platform the path comes from
+    let formats = CustomFormats::from_toml(
+        r#"
+        [[format]]
+        name = "Test Name"
+        extension = "qua"
+        filename = '^sample\.'
+        "#,
+    )
+    .unwrap();
+    assert!(formats
+        .find(r"C:\Quarantine.d\sample.qua", b"")
+        .unwrap()
+        .is_some());
+    assert!(formats
+        .find(r"C:\Quarantine.qua\sample", b"")
+        .unwrap()
+        .is_none());
+
+    let formats = CustomFormats::from_file("examples/formats.toml");
+    assert!(formats.is_ok());
+}
+
+#[test]
+fn test_requarantine() {
+    use crate::vendors;
+
+    let payload = b"MZ synthetic payload".to_vec();
+    let meta = Metadata::default();
+    let enc = vendors::avast::quarantine(&payload, &meta).unwrap();
+    assert_eq!(
+        vendors::avast::unquarantine(&enc).unwrap(),
+        vec![payload.clone()]
+    );
+    let enc = vendors::k7::quarantine(&payload, &meta).unwrap();
+    assert_eq!(
+        vendors::k7::unquarantine(&enc).unwrap(),
+        vec![payload.clone()]
+    );
+    let enc = vendors::microsoft::pc_quarantine(&payload, &meta).unwrap();
+    assert_eq!(
+        vendors::microsoft::pc_unquarantine(&enc).unwrap(),
+        vec![payload.clone()]
+    );
+    let enc = vendors::others::zip_quarantine(&payload, Some(b"infected")).unwrap();
+    assert_eq!(
+        vendors::others::zip_unquarantine(&enc, Some(b"infected")).unwrap(),
+        vec![payload]
+    );
+}
+
+/// md5 of the payload wrapped by the golden files
+const PAYLOAD_MD5: &str = "9b7bb59d7e805a36327f6b0317a2672e";
+
+/// Metadata the golden files are written with, see `golden_metadata`
+const FIXTURE_PATH: &str = "C:\\Users\\victim\\Downloads\\sample.exe";
+const FIXTURE_THREAT: &str = "Unquarantine.Test.Fixture";
+const FIXTURE_TIME: &str = "1640995200";
+
+/// No metadata expected
+const NO_METADATA: &[(&str, &str)] = &[];
+
+/// (file, expected vendor, md5 of the restored payload, expected metadata as
+/// (field, value) pairs: `original_path`, `threat_name`, `quarantine_time`
+/// and the extra keys)
+type Expected = (
+    &'static str,
+    &'static str,
+    &'static str,
+    &'static [(&'static str, &'static str)],
+);
+
+/// Synthetic files in `data/golden`, see `examples/fixtures.rs`.
+/// They are written by the crate's own encoders, so they only check that
+/// detection and decoding invert the encoders; they say nothing about
+/// compatibility with files written by the products.
+const GOLDEN: &[Expected] = &[
+    ("sample.v3b", "AhnLab V3B files", PAYLOAD_MD5, NO_METADATA),
+    ("sample.ifc", "Amiti IFC Files", PAYLOAD_MD5, NO_METADATA),
+    ("sample.eqf", "ASquared EQF Files", PAYLOAD_MD5, NO_METADATA),
+    (
+        "sample.chest",
+        "Avast/AVG chest files",
+        PAYLOAD_MD5,
+        NO_METADATA,
+    ),
+    ("sample.qua", "Avira QUA Files", PAYLOAD_MD5, NO_METADATA),
+    ("sample.qv", "Baidu QV Files", PAYLOAD_MD5, NO_METADATA),
+    (
+        "sample.bdq",
+        "BitDefender/Lavasoft AdAware/Total Defence: BDQ Files",
+        PAYLOAD_MD5,
+        NO_METADATA,
+    ),
+    (
+        "bullguard.q",
+        "BullGuard Q Files",
+        "43416fb7994c738e73af35e884feb868",
+        NO_METADATA,
+    ),
+    ("sample.qrt", "Cisco AMP", PAYLOAD_MD5, NO_METADATA),
+    (
+        "sample.cmc",
+        "CMC Antivirus CMC Files",
+        PAYLOAD_MD5,
+        NO_METADATA,
+    ),
+    ("sample.vir", "ESafe VIR Files", PAYLOAD_MD5, NO_METADATA),
+    ("sample.nqf", "ESET NQF Files", PAYLOAD_MD5, NO_METADATA),
+    ("sample.tmp", "F-Prot TMP Files", PAYLOAD_MD5, NO_METADATA),
+    ("sample.q", "G-Data Q Files", PAYLOAD_MD5, NO_METADATA),
+    (
+        "9b7bb59d7e805a36327f6b0317a2672e.QNT",
+        "K7 QNT files",
+        PAYLOAD_MD5,
+        &[("md5", PAYLOAD_MD5)],
+    ),
+    (
+        "sample.klq",
+        "Kaspersky KLQ files",
+        PAYLOAD_MD5,
+        NO_METADATA,
+    ),
+    (
+        "9b7bb59d7e805a36327f6b0317a2672e.bin",
+        "Kaspersky System Watcher files",
+        PAYLOAD_MD5,
+        &[("md5", PAYLOAD_MD5)],
+    ),
+    ("sample.lqf", "Lumension LEMSS", PAYLOAD_MD5, NO_METADATA),
+    (
+        "sample.quar",
+        "MalwareBytes DATA and QUAR Files",
+        PAYLOAD_MD5,
+        &[("id", "sample")],
+    ),
+    (
+        "sample.data",
+        "MalwareBytes DATA and QUAR Files",
+        PAYLOAD_MD5,
+        &[("id", "sample")],
+    ),
+    (
+        "{3f2504e0-4f89-11d3-9a0c-0305e82c3301}-sample",
+        "Microsoft Antimalware / Microsoft Security Essentials",
+        PAYLOAD_MD5,
+        NO_METADATA,
+    ),
+    (
+        "3f2504e0-4f89-11d3-9a0c-0305e82c3301-mac",
+        "Microsoft Defender MAC",
+        "f90186b4674781b01689b2ecc2f6c29c",
+        NO_METADATA,
+    ),
+    (
+        "clamtk/viruses/sample.exe.VIRUS",
+        "ClamTk quarantine",
+        PAYLOAD_MD5,
+        &[
+            ("original_path", FIXTURE_PATH),
+            ("threat_name", FIXTURE_THREAT),
+            ("md5", PAYLOAD_MD5),
+            ("mode", "0644"),
+        ],
+    ),
+    (
+        "mdatp/quarantine/ResourceData/E3/E3C53DEFF56373B9A95E371CF0FA808654995691",
+        "Microsoft Defender for Endpoint (Linux/macOS)",
+        PAYLOAD_MD5,
+        &[
+            ("original_path", FIXTURE_PATH),
+            ("threat_name", FIXTURE_THREAT),
+            ("quarantine_time", FIXTURE_TIME),
+        ],
+    ),
+    (
+        "sample.defender",
+        "Microsoft Windows Defender (PC)",
+        PAYLOAD_MD5,
+        NO_METADATA,
+    ),
+    (
+        "3f2504e0-4f89-11d3-9a0c-0305e82c3301",
+        "Panda <GUID> Zip Files",
+        PAYLOAD_MD5,
+        NO_METADATA,
+    ),
+    (
+        "sample.mal",
+        "Sentinel One MAL files",
+        PAYLOAD_MD5,
+        NO_METADATA,
+    ),
+    (
+        "spybot-1.zip",
+        "Spybot - Search & Destroy 2 Zip Files",
+        PAYLOAD_MD5,
+        NO_METADATA,
+    ),
+    (
+        "sample.sdb",
+        "SUPERAntiSpyware (SDB)",
+        PAYLOAD_MD5,
+        NO_METADATA,
+    ),
+    (
+        "sample.qbd",
+        "Symantec QBD and QBI Files",
+        PAYLOAD_MD5,
+        NO_METADATA,
+    ),
+    (
+        "{3f2504e0-4f89-11d3-9a0c-0305e82c3301}",
+        "Symantec ccSubSDK {GUID} Files",
+        PAYLOAD_MD5,
+        NO_METADATA,
+    ),
+    (
+        "submissions.idx",
+        "Symantec ccSubSDK submissions.idx Files",
+        PAYLOAD_MD5,
+        NO_METADATA,
+    ),
+    ("sample.vbn", "Symantec VBN Files", PAYLOAD_MD5, NO_METADATA),
+    (
+        "quarantine.qtn",
+        "Symantec quarantine.qtn",
+        PAYLOAD_MD5,
+        &[
+            ("OriginalPath", FIXTURE_PATH),
+            ("QuarantineDate", FIXTURE_TIME),
+            ("ThreatName", FIXTURE_THREAT),
+        ],
+    ),
+    (
+        "3f2504e0-4f89-11d3-9a0c-0305e82c3301.dat",
+        "Total AV {GUID}.dat",
+        PAYLOAD_MD5,
+        NO_METADATA,
+    ),
+    (
+        "sample.vsbx",
+        "TrendMicro VSBX files",
+        PAYLOAD_MD5,
+        &[
+            ("original_path", FIXTURE_PATH),
+            ("encryption_method", "2"),
+            ("variant", "OfficeScan (CRC32C key stream)"),
+        ],
+    ),
+    (
+        "{3f2504e0-4f89-11d3-9a0c-0305e82c3301}_ENC2",
+        "Vipre <GUID>_ENC2 Files",
+        PAYLOAD_MD5,
+        NO_METADATA,
+    ),
+    (
+        "9b7bb59d7e805a36327f6b0317a2672e",
+        "QuickHeal Files",
+        PAYLOAD_MD5,
+        NO_METADATA,
+    ),
+    (
+        "zemana/9b7bb59d7e805a36327f6b0317a2672e",
+        "Zemana Files",
+        PAYLOAD_MD5,
+        NO_METADATA,
+    ),
+];
+
+/// Real quarantine files in `data/`, the only ones not written by the crate.
+/// The McAfee BUP sample is covered by `test_mcafee`.
+const SAMPLES: &[Expected] = &[
+    (
+        "99E865BA2BBCED427E8CB4785CCE58DDCCCE8337",
+        "Microsoft Windows Defender (PC)",
+        "44d88612fea8a8f36de82e1278abb02f",
+        NO_METADATA,
+    ),
+    (
+        "56698C1DAC9BA511A963B040C01132665CC8628D",
+        "Microsoft Windows Defender (PC)",
+        "e4968ef99266df7c9a1f0637d2389dab",
+        NO_METADATA,
+    ),
+];
+
+/// `metadata` as (field, value) pairs, sorted
+fn metadata_fields(metadata: &Metadata) -> Vec<(String, String)> {
+    let mut fields: Vec<_> = metadata
+        .extra
+        .iter()
+        .map(|(k, v)| (k.clone(), v.clone()))
+        .collect();
+    if let Some(path) = &metadata.original_path {
+        fields.push(("original_path".to_string(), path.clone()));
+    }
+    if let Some(threat) = &metadata.threat_name {
+        fields.push(("threat_name".to_string(), threat.clone()));
+    }
+    if let Some(time) = metadata.quarantine_time {
+        fields.push(("quarantine_time".to_string(), time.to_string()));
+    }
+    fields.sort();
+    fields
+}
+
+#[test]
+fn test_golden_round_trip() {
+    use md5::Digest;
+
+    for (dir, table) in [("data/golden", GOLDEN), ("data", SAMPLES)] {
+        for (file, vendor, md5, metadata) in table {
+            let path = format!("{}/{}", dir, file);
+            let result =
+                UnQuarantine::from_file(&path).unwrap_or_else(|e| panic!("{}: {}", file, e));
+            assert_eq!(result.get_vendor(), *vendor, "{}", file);
+            let buffers = result.get_unquarantined_buffer();
+            assert_eq!(buffers.len(), 1, "{}", file);
+            assert_eq!(
+                format!("{:x}", md5::Md5::digest(&buffers[0])),
+                *md5,
+                "{}",
+                file
+            );
+            let mut expected: Vec<_> = metadata
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect();
+            expected.sort();
+            assert_eq!(
+                metadata_fields(&result.get_metadata()[0]),
+                expected,
+                "{}",
+                file
+            );
+        }
+    }
+}
+
+#[test]
+fn test_typed_errors() {
+    use crate::{error::Error, vendors};
+
+    let mut data = vec![0u8; 0x180];
+    data[0x128] = 0x08;
+    assert!(vendors::k7::unquarantine(&data).is_ok());
+    data[0x128] = 0xFF;
+    assert!(matches!(
+        vendors::k7::unquarantine(&data),
+        Err(Error::LengthOutOfRange {
+            offset: 0x128,
+            value: 0xFF,
+            file_len: 0x180,
+            ..
+        })
+    ));
+    assert!(matches!(
+        vendors::kaspersky::av_unquarantine(b"KLQ"),
+        Err(Error::BadMagic { .. })
+    ));
+
+    let mut data = vendors::baidu::quarantine(&[0x4D; 64], &Metadata::default()).unwrap();
+    // the single chunk: u16 length, then 5 bytes of stored block header and the data
+    let at = data.len() - 71;
+    data[at..at + 2].copy_from_slice(&0xFFFFu16.to_le_bytes());
+    assert!(matches!(
+        vendors::baidu::unquarantine(&data),
+        Err(Error::LengthOutOfRange {
+            field: "chunk length",
+            value: 0xFFFF,
+            ..
+        })
+    ));
+
+    // the plain XOR fallbacks accept anything, as a low confidence guess
+    let tmp = TempDir::new("typed-errors");
+    let file = tmp.path().join("not-quarantined.txt");
+    std::fs::write(&file, b"just some text, not a quarantined file").unwrap();
+    let (result, trace) = UnQuarantine::explain(file.to_str().unwrap(), None);
+    let result = result.unwrap();
+    assert_eq!(result.get_vendor(), "Vipre <GUID>_ENC2 Files");
+    assert_eq!(result.get_confidence(), crate::Confidence::Low);
+    let (vendor, error) = trace.attempts().next().unwrap();
+    assert_eq!(vendor, "Kaspersky Antivirus");
+    assert!(error.is_some());
+    let rejected = result.get_rejected();
+    assert_eq!(rejected.len(), 4);
+    assert_eq!(rejected[0].0, "Kaspersky Antivirus");
+
+    // an empty file leaves nothing to guess from
+    let file = tmp.path().join("empty.txt");
+    std::fs::write(&file, b"").unwrap();
+    let result = UnQuarantine::from_file(file.to_str().unwrap());
+    match result {
+        Err(Error::NoMatchingVendor { attempts, .. }) => assert_eq!(attempts.len(), 4),
+        _ => panic!("expected NoMatchingVendor"),
+    }
+
+    // truncated files are errors, not panics
+    type Unquarantine = fn(&[u8]) -> crate::Result<Vec<Vec<u8>>>;
+    let vendors: [Unquarantine; 6] = [
+        vendors::ahnlab::unquarantine,
+        vendors::asquared::unquarantine,
+        vendors::cmc::unquarantine,
+        vendors::fprot::unquarantine,
+        vendors::lumension::unquarantine,
+        vendors::symantec::cc_sub_sdk_unquarantine,
+    ];
+    for unquarantine in vendors {
+        assert!(matches!(
+            unquarantine(&[0xFF; 24]),
+            Err(Error::LengthOutOfRange { .. })
+        ));
+    }
+    let mut data = vec![0u8; 0x60];
+    data[58..62].copy_from_slice(&0x7FFF_FFFFu32.to_le_bytes());
+    assert!(vendors::ahnlab::unquarantine(&data).is_err());
+}
+
+#[test]
+fn test_explain() {
+    use crate::trace::Step;
+
+    let (result, trace) = UnQuarantine::explain("data/golden/sample.bdq", None);
+    assert!(result.is_ok());
+    assert!(trace.steps().contains(&Step::Check {
+        check: "extension is .bdq".to_string(),
+        passed: true,
+    }));
+    assert_eq!(
+        trace.steps().last(),
+        Some(&Step::Attempt {
+            vendor: "BitDefender/Lavasoft AdAware/Total Defence: BDQ Files".to_string(),
+            error: None,
+        })
+    );
+    assert!(trace.to_string().contains("[ ] extension is .v3b"));
+}
+
+/// Metadata every golden fixture was quarantined with
+fn golden_metadata() -> Metadata {
+    Metadata {
+        original_path: Some(FIXTURE_PATH.to_string()),
+        threat_name: Some(FIXTURE_THREAT.to_string()),
+        quarantine_time: Some(1_640_995_200),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_malwarebytes_metadata() {
+    use crate::{error::Error, vendors::malwarebytes};
+
+    let result = UnQuarantine::from_file("data/golden/sample.quar").unwrap();
+    assert_eq!(result.get_metadata()[0].extra["id"], "sample");
+    let record =
+        malwarebytes::quarantine(b"[Quarantine]\r\nid=x\r\n", &Metadata::default()).unwrap();
+    assert!(matches!(
+        malwarebytes::parse_data(&record),
+        Err(Error::JsonError(_))
+    ));
+
+    // a record that is not JSON is returned decrypted, and does not keep
+    // the .quar file next to it from being restored
+    let tmp = TempDir::new("malwarebytes");
+    let data = tmp.path().join("sample.data");
+    std::fs::write(&data, &record).unwrap();
+    let result = UnQuarantine::from_file(data.to_str().unwrap()).unwrap();
+    assert_eq!(
+        result.get_unquarantined_buffer()[0],
+        b"[Quarantine]\r\nid=x\r\n"
+    );
+    let quar = tmp.path().join("sample.quar");
+    std::fs::copy("data/golden/sample.quar", &quar).unwrap();
+    let result = UnQuarantine::from_file(quar.to_str().unwrap()).unwrap();
+    assert!(result.get_metadata()[0].is_empty());
+    assert_eq!(
+        result.get_unquarantined_buffer(),
+        UnQuarantine::from_file("data/golden/sample.quar")
+            .unwrap()
+            .get_unquarantined_buffer()
+    );
+}
+
+#[test]
+fn test_mcafee_details() {
+    use crate::vendors::mcafee::details_metadata;
+
+    let details = "[Details]\r\nDetectionName=Unquarantine.Test.Fixture\r\nDetectionType=1\r\n\
+        TimeYear=2022\r\nTimeMonth=1\r\nTimeDay=1\r\nTimeHour=0\r\nTimeMinute=0\r\nTimeSecond=0\r\n\
+        NumberOfFiles=1\r\n[File_0]\r\nObjectType=5\r\n\
+        OriginalName=C:\\Users\\victim\\Downloads\\sample.exe\r\n\
+        CreationYear=2021\r\nCreationMonth=12\r\nCreationDay=31\r\n\
+        CreationHours=23\r\nCreationMinutes=59\r\nCreationSeconds=59\r\n";
+    let entries = details_metadata(details);
+    let file = &entries["File_0"];
+    assert_eq!(file.original_path, golden_metadata().original_path);
+    assert_eq!(file.threat_name, golden_metadata().threat_name);
+    assert_eq!(file.quarantine_time, golden_metadata().quarantine_time);
+    assert_eq!(file.extra["creation_time"], "1640995199");
+    assert_eq!(file.extra["ObjectType"], "5");
+    assert_eq!(entries["Details"].extra["NumberOfFiles"], "1");
+}
+
+#[test]
+fn test_gdata_blocks() {
+    use crate::{error::Error, vendors::gdata};
+
+    // the header and body blocks are skipped whatever they hold
+    let mut data = vec![0xCA, 0xFE, 0xBA, 0xBE, 3, 0, 0, 0, 1, 2, 3];
+    data.extend([0xBA, 0xAD, 0xF0, 0x0D, 2, 0, 0, 0, 4, 5]);
+    let payload = gdata::quarantine(b"MZ synthetic payload", &Metadata::default()).unwrap();
+    data.extend(&payload[16..]);
+    assert_eq!(
+        gdata::unquarantine(&data).unwrap(),
+        vec![b"MZ synthetic payload".to_vec()]
+    );
+
+    data[4] = 0xFF;
+    assert!(matches!(
+        gdata::unquarantine(&data),
+        Err(Error::LengthOutOfRange {
+            field: "header",
+            ..
+        })
+    ));
+    assert!(!gdata::is_gdata(
+        &std::fs::read("data/golden/bullguard.q").unwrap()
+    ));
+}
+
+#[test]
+fn test_avira_confidence() {
+    use crate::{vendors::avira, Confidence};
+
+    let result = UnQuarantine::from_file("data/golden/sample.qua").unwrap();
+    assert_eq!(result.get_confidence(), Confidence::High);
+
+    // same layout without the magic: matched by extension only
+    let mut data = std::fs::read("data/golden/sample.qua").unwrap();
+    data[..4].copy_from_slice(b"XXXX");
+    let tmp = TempDir::new("avira");
+    let file = tmp.path().join("no-magic.qua");
+    std::fs::write(&file, &data).unwrap();
+    let result = UnQuarantine::from_file(file.to_str().unwrap()).unwrap();
+    assert_eq!(result.get_vendor(), "Avira QUA Files");
+    assert_eq!(result.get_confidence(), Confidence::Low);
+
+    data[16..20].copy_from_slice(&u32::MAX.to_le_bytes());
+    assert!(avira::unquarantine(&data).is_err());
+}
+
+#[test]
+fn test_k7_verification() {
+    use crate::{error::Error, vendors::k7};
+
+    let file = format!("data/golden/{}.QNT", PAYLOAD_MD5);
+    let result = UnQuarantine::from_file(&file).unwrap();
+    assert_eq!(result.get_metadata()[0].extra["md5"], PAYLOAD_MD5);
+
+    let data = std::fs::read(&file).unwrap();
+    assert!(matches!(
+        k7::restore("00000000000000000000000000000000.QNT", &data),
+        Err(Error::ChecksumMismatch { .. })
+    ));
+    assert!(k7::restore("renamed.QNT", &data).is_ok());
+}
+
+#[test]
+fn test_symantec_qtn_metadata() {
+    use crate::utils::zip_stored;
+    use crate::vendors::symantec;
+
+    let result = UnQuarantine::from_file("data/golden/quarantine.qtn").unwrap();
+    assert_eq!(result.get_unquarantined_buffer().len(), 1);
+    // the keys are kept as they are
+    let meta = &result.get_metadata()[0];
+    assert_eq!(meta.original_path, None);
+    assert_eq!(
+        meta.extra["OriginalPath"],
+        golden_metadata().original_path.unwrap()
+    );
+    assert_eq!(
+        meta.extra["ThreatName"],
+        golden_metadata().threat_name.unwrap()
+    );
+    assert_eq!(meta.extra["QuarantineDate"], "1640995200");
+
+    // members without a property list are returned as they are
+    let data = zip_stored(&[("file", b"MZ plain")], None);
+    let restored = symantec::qtn_restore(&data).unwrap();
+    assert_eq!(restored[0].data, b"MZ plain");
+    assert!(restored[0].metadata.is_empty());
+
+    // top level items are paired with their own property list
+    let plist = |threat: &str| {
+        let mut dict = plist::Dictionary::new();
+        dict.insert("ThreatName".to_string(), threat.into());
+        let mut out = vec![];
+        plist::Value::Dictionary(dict)
+            .to_writer_xml(&mut out)
+            .unwrap();
+        out
+    };
+    let (a, b) = (plist("Threat.A"), plist("Threat.B"));
+    let data = zip_stored(
+        &[
+            ("a.exe.plist", &a[..]),
+            ("a.exe", b"MZ a"),
+            ("b.exe.plist", &b[..]),
+            ("b.exe", b"MZ b"),
+        ],
+        None,
+    );
+    let restored = symantec::qtn_restore(&data).unwrap();
+    assert_eq!(restored.len(), 2);
+    assert_eq!(restored[0].metadata.extra["ThreatName"], "Threat.A");
+    assert_eq!(restored[1].metadata.extra["ThreatName"], "Threat.B");
+
+    // unpaired or unparsable property lists are payloads themselves
+    let data = zip_stored(
+        &[
+            ("com.evil.agent.plist", &a[..]),
+            ("c.exe.plist", b"not a plist"),
+            ("c.exe", b"MZ c"),
+        ],
+        None,
+    );
+    let restored = symantec::qtn_restore(&data).unwrap();
+    assert_eq!(restored.len(), 3);
+    assert_eq!(restored[0].data, b"MZ c");
+    assert!(restored[0].metadata.is_empty());
+    assert_eq!(restored[1].data, a);
+    assert_eq!(restored[2].data, b"not a plist");
+}
+
+#[test]
+fn test_path_independent_names() {
+    use crate::{
+        patterns::GUID_PATTERN,
+        trace::{Probe, Trace},
+        utils::{file_name, parent_dirs, sibling},
+    };
+
+    let qf = r"C:\ProgramData\Microsoft\Windows Defender\Quarantine\ResourceData\9B\9B7BB59D";
+    assert_eq!(file_name(qf), "9B7BB59D");
+    assert_eq!(parent_dirs(qf).nth(1), Some("ResourceData"));
+    assert_eq!(sibling("a/b\\c.quar", "c.data"), "a/b\\c.data");
+    let mut trace = Trace::default();
+    let mut p = Probe::new(qf, vec![], &mut trace);
+    assert!(p.in_dir("resourcedata"));
+    assert!(!p.in_dir("9B7BB59D"));
+
+    let qf = r"D:\{3f2504e0-4f89-11d3-9a0c-0305e82c3301}\sample.vbn";
+    let mut p = Probe::new(qf, vec![], &mut trace);
+    assert!(p.ext("vbn"));
+    assert!(!p.name_matches(&GUID_PATTERN));
+
+    // a {GUID} folder no longer makes every file in it a ccSubSDK file
+    let tmp = TempDir::new("guid-folder");
+    let dir = tmp.path().join("{3f2504e0-4f89-11d3-9a0c-0305e82c3301}");
+    std::fs::create_dir_all(&dir).unwrap();
+    let file = dir.join("sample.vbn");
+    std::fs::copy("data/golden/sample.vbn", &file).unwrap();
+    let result = UnQuarantine::from_file(file.to_str().unwrap()).unwrap();
+    assert_eq!(result.get_vendor(), "Symantec VBN Files");
+}
+
+#[test]
+fn test_kaspersky_verification() {
+    use crate::{error::Error, vendors::kaspersky};
+
+    let data = std::fs::read(format!("data/golden/{}.bin", PAYLOAD_MD5)).unwrap();
+    assert!(matches!(
+        kaspersky::system_watcher_restore("ffffffffffffffffffffffffffffffff.bin", &data),
+        Err(Error::ChecksumMismatch { .. })
+    ));
+    // a .bin file that does not hash to its name is not System Watcher's
+    let tmp = TempDir::new("kaspersky");
+    let file = tmp.path().join("ffffffffffffffffffffffffffffffff.bin");
+    std::fs::write(&file, &data).unwrap();
+    let (result, trace) = UnQuarantine::explain(file.to_str().unwrap(), None);
+    assert_ne!(
+        result.map(|r| r.get_vendor().to_string()).ok().as_deref(),
+        Some("Kaspersky System Watcher files")
+    );
+    assert!(trace
+        .attempts()
+        .any(|(vendor, e)| vendor == "Kaspersky System Watcher files" && e.is_some()));
+}
+
+#[test]
+fn test_defender_endpoint_paths() {
+    // a ResourceData blob outside of the mdatp or macOS Defender folders is
+    // a Windows one
+    let tmp = TempDir::new("defender-endpoint");
+    let dir = tmp
+        .path()
+        .join("quarantine")
+        .join("ResourceData")
+        .join("9B");
+    std::fs::create_dir_all(&dir).unwrap();
+    let file = dir.join("9B7BB59D7E805A36327F6B0317A2672E");
+    std::fs::copy("data/golden/sample.defender", &file).unwrap();
+    let result = UnQuarantine::from_file(file.to_str().unwrap()).unwrap();
+    assert_eq!(result.get_vendor(), "Microsoft Windows Defender (PC)");
+
+    let dir = tmp
+        .path()
+        .join("Microsoft")
+        .join("Defender")
+        .join("quarantine")
+        .join("ResourceData")
+        .join("9B");
+    std::fs::create_dir_all(&dir).unwrap();
+    let file = dir.join("9B7BB59D7E805A36327F6B0317A2672E");
+    std::fs::copy("data/golden/sample.defender", &file).unwrap();
+    let result = UnQuarantine::from_file(file.to_str().unwrap()).unwrap();
+    assert_eq!(
+        result.get_vendor(),
+        "Microsoft Defender for Endpoint (Linux/macOS)"
+    );
+    // no Entries folder, no metadata
+    assert!(result.get_metadata()[0].is_empty());
+}
+
+#[test]
+fn test_defender_entries_metadata() {
+    use crate::{error::Error, vendors::microsoft};
+
+    let result = UnQuarantine::from_file(
+        "data/golden/mdatp/quarantine/ResourceData/E3/E3C53DEFF56373B9A95E371CF0FA808654995691",
+    )
+    .unwrap();
+    assert_eq!(result.get_metadata().to_vec(), vec![golden_metadata()]);
+
+    let sha1 = [0xAB; 20];
+    let mut meta = golden_metadata();
+    meta.original_path = Some(r"\\?\C:\Users\victim\sample.exe".to_string());
+    let entries = microsoft::parse_entries(&microsoft::entries_quarantine(&sha1, &meta).unwrap());
+    let (hash, meta) = &entries.unwrap()[0];
+    assert_eq!(hash, &"AB".repeat(20));
+    assert_eq!(
+        meta.original_path.as_deref(),
+        Some(r"C:\Users\victim\sample.exe")
+    );
+    assert!(matches!(
+        microsoft::parse_entries(&[0; 0x20]),
+        Err(Error::LengthOutOfRange { .. })
+    ));
+}
+
+#[test]
+fn test_trendmicro_variants() {
+    use crate::{error::Error, vendors::trendmicro};
+
+    for (method, variant) in [
+        (0, "OfficeScan (unencrypted)"),
+        (2, "OfficeScan (CRC32C key stream)"),
+    ] {
+        let data =
+            trendmicro::quarantine_with_method(b"MZ synthetic payload", &golden_metadata(), method)
+                .unwrap();
+        let restored = trendmicro::restore(&data).unwrap();
+        assert_eq!(restored.data, b"MZ synthetic payload");
+        assert_eq!(restored.metadata.extra["variant"], variant);
+        assert_eq!(
+            restored.metadata.original_path,
+            golden_metadata().original_path
+        );
+    }
+    let mut data = trendmicro::quarantine_with_method(b"MZ", &Metadata::default(), 0).unwrap();
+    // tag 7 of an untagged file sits right after tag 6: 10 + 3 + 4 + 3
+    data[20] = !3;
+    let restored = trendmicro::restore(&data).unwrap();
+    assert_eq!(restored.data, b"MZ");
+    assert_eq!(
+        restored.metadata.extra["variant"],
+        "unknown encryption method"
+    );
+    assert_eq!(restored.metadata.extra["encryption_method"], "3");
+    assert!(matches!(
+        trendmicro::quarantine_with_method(b"MZ", &Metadata::default(), 3),
+        Err(Error::UnsupportedVersion { version: 3, .. })
+    ));
+}
+
+#[test]
+fn test_clamtk_metadata() {
+    use crate::{vendors::clamav, Confidence};
+
+    let result = UnQuarantine::from_file("data/golden/clamtk/viruses/sample.exe.VIRUS").unwrap();
+    let meta = &result.get_metadata()[0];
+    assert_eq!(meta.original_path, golden_metadata().original_path);
+    assert_eq!(meta.threat_name, golden_metadata().threat_name);
+    assert_eq!(meta.extra["md5"], PAYLOAD_MD5);
+    assert_eq!(meta.extra["mode"], "0644");
+
+    // logs are read newest first by the date in their name, not by name
+    let payload = std::fs::read("data/golden/clamtk/viruses/sample.exe.VIRUS").unwrap();
+    let tmp = TempDir::new("clamtk");
+    let root = tmp.path();
+    let viruses = root.join("viruses");
+    let history = root.join(clamav::HISTORY_DIR);
+    std::fs::create_dir_all(&viruses).unwrap();
+    std::fs::create_dir_all(&history).unwrap();
+    std::fs::write(
+        root.join(clamav::RESTORE_FILE),
+        clamav::clamtk_restore_entry(&payload, &golden_metadata()),
+    )
+    .unwrap();
+    std::fs::write(
+        history.join("Feb-01-2022.log"),
+        clamav::clamtk_history_entry(&golden_metadata()),
+    )
+    .unwrap();
+    let old = Metadata {
+        threat_name: Some("Old.Threat".to_string()),
+        ..golden_metadata()
+    };
+    std::fs::write(
+        history.join("Mar-01-2021.log"),
+        clamav::clamtk_history_entry(&old),
+    )
+    .unwrap();
+    let file = viruses.join("sample.exe.VIRUS");
+    std::fs::write(&file, &payload).unwrap();
+    let result = UnQuarantine::from_file(file.to_str().unwrap()).unwrap();
+    assert_eq!(
+        result.get_metadata()[0].threat_name,
+        golden_metadata().threat_name
+    );
+
+    // a copy ClamTk has no record of is only a guess
+    let file = viruses.join("other.VIRUS");
+    std::fs::write(&file, b"plain copy").unwrap();
+    let result = UnQuarantine::from_file(file.to_str().unwrap()).unwrap();
+    assert_eq!(result.get_vendor(), "ClamAV quarantine");
+    assert_eq!(result.get_confidence(), Confidence::Low);
+
+    // only files directly in the viruses folder are looked at
+    let nested = viruses.join("nested");
+    std::fs::create_dir_all(&nested).unwrap();
+    let file = nested.join("sample.exe.VIRUS");
+    std::fs::write(&file, &payload).unwrap();
+    let (result, _) = UnQuarantine::explain(file.to_str().unwrap(), None);
+    assert!(!matches!(result, Ok(r) if r.get_vendor() == "ClamTk quarantine"));
+}
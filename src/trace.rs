@@ -0,0 +1,244 @@
+//! Explain mode: a record of every check the dispatcher evaluated.
+//!
+//! ## Example Usage
+//! ```rust
+//! use unquarantine::UnQuarantine;
+//!
+//! let (result, trace) = UnQuarantine::explain("data/99E865BA2BBCED427E8CB4785CCE58DDCCCE8337", None);
+//! assert!(result.is_ok());
+//! println!("{}", trace);
+//! ```
+use crate::{
+    error::Error,
+    metadata::Restored,
+    utils::{file_name, parent_dirs, sibling},
+    Confidence, Result, UnQuarantine,
+};
+use regex::Regex;
+use std::{fmt, path::Path};
+
+/// A single event recorded while dispatching a file
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Step {
+    /// A condition on the file name or content, and whether it held
+    Check { check: String, passed: bool },
+    /// A vendor decoder that was run, with the error when it failed
+    Attempt {
+        vendor: String,
+        error: Option<String>,
+    },
+}
+
+/// Everything the dispatcher looked at for one file, in order
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Trace {
+    steps: Vec<Step>,
+}
+
+impl Trace {
+    /// The recorded steps
+    pub fn steps(&self) -> &[Step] {
+        &self.steps
+    }
+
+    /// The decoders that were run, with their errors
+    pub fn attempts(&self) -> impl Iterator<Item = (&str, Option<&str>)> {
+        self.steps.iter().filter_map(|step| match step {
+            Step::Attempt { vendor, error } => Some((vendor.as_str(), error.as_deref())),
+            _ => None,
+        })
+    }
+
+    pub(crate) fn check(&mut self, check: String, passed: bool) -> bool {
+        self.steps.push(Step::Check { check, passed });
+        passed
+    }
+
+    pub(crate) fn attempt<T>(&mut self, vendor: &str, res: &Result<T>) {
+        self.steps.push(Step::Attempt {
+            vendor: vendor.to_string(),
+            error: res.as_ref().err().map(Error::to_string),
+        });
+    }
+}
+
+impl fmt::Display for Trace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for step in &self.steps {
+            match step {
+                Step::Check { check, passed } => {
+                    writeln!(f, "[{}] {}", if *passed { 'x' } else { ' ' }, check)?
+                }
+                Step::Attempt {
+                    vendor,
+                    error: None,
+                } => writeln!(f, "--> {}: restored", vendor)?,
+                Step::Attempt {
+                    vendor,
+                    error: Some(e),
+                } => writeln!(f, "--> {}: rejected: {}", vendor, e)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The file being dispatched; every check goes through here so it is traced
+pub(crate) struct Probe<'t> {
+    pub qf: &'t str,
+    pub data: Vec<u8>,
+    /// The file name without its folders, whichever separator the path uses
+    name: &'t str,
+    extension: String,
+    trace: &'t mut Trace,
+    /// The first low confidence match, used if nothing better is found
+    candidate: Option<UnQuarantine<'static>>,
+}
+
+impl<'t> Probe<'t> {
+    pub fn new(qf: &'t str, data: Vec<u8>, trace: &'t mut Trace) -> Self {
+        let name = file_name(qf);
+        let extension = name
+            .rsplit_once('.')
+            .filter(|(stem, _)| !stem.is_empty())
+            .map(|(_, ext)| ext)
+            .unwrap_or_default()
+            .to_string();
+        Self {
+            qf,
+            data,
+            name,
+            extension,
+            trace,
+            candidate: None,
+        }
+    }
+
+    /// The file extension equals `ext` (case insensitive)
+    pub fn ext(&mut self, ext: &str) -> bool {
+        let passed = self.extension.eq_ignore_ascii_case(ext);
+        self.trace.check(format!("extension is .{}", ext), passed)
+    }
+
+    /// The file extension starts with `prefix` (case insensitive)
+    pub fn ext_prefix(&mut self, prefix: &str) -> bool {
+        let passed = self
+            .extension
+            .to_ascii_lowercase()
+            .starts_with(&prefix.to_ascii_lowercase());
+        self.trace
+            .check(format!("extension starts with .{}", prefix), passed)
+    }
+
+    /// The file starts with `magic`
+    pub fn magic(&mut self, magic: &[u8]) -> bool {
+        let passed = self.data.starts_with(magic);
+        self.trace
+            .check(format!("magic {} at offset 0", describe(magic)), passed)
+    }
+
+    /// The file name matches `re`
+    pub fn name_matches(&mut self, re: &Regex) -> bool {
+        let passed = re.is_match(self.name);
+        self.trace
+            .check(format!("file name matches /{}/", re.as_str()), passed)
+    }
+
+    /// The file name ends with `suffix` (case insensitive)
+    pub fn name_ends_with(&mut self, suffix: &str) -> bool {
+        let passed = self.name.to_ascii_lowercase().ends_with(suffix);
+        self.trace
+            .check(format!("file name ends with '{}'", suffix), passed)
+    }
+
+    /// The file name is `name` (case insensitive)
+    pub fn name_is(&mut self, name: &str) -> bool {
+        let passed = self.name.eq_ignore_ascii_case(name);
+        self.trace.check(format!("file name is '{}'", name), passed)
+    }
+
+    /// One of the folders the file is in is named `dir` (case insensitive);
+    /// only for folders a product always uses, as a deliberate hint
+    pub fn in_dir(&mut self, dir: &str) -> bool {
+        let passed = parent_dirs(self.qf).any(|d| d.eq_ignore_ascii_case(dir));
+        self.trace
+            .check(format!("file is inside a '{}' folder", dir), passed)
+    }
+
+    /// The folder the file is directly in is named `dir` (case insensitive)
+    pub fn parent_is(&mut self, dir: &str) -> bool {
+        let passed = parent_dirs(self.qf)
+            .next()
+            .is_some_and(|d| d.eq_ignore_ascii_case(dir));
+        self.trace
+            .check(format!("file is directly in a '{}' folder", dir), passed)
+    }
+
+    /// A file named `name` is in the same folder
+    pub fn sibling_exists(&mut self, name: &str) -> bool {
+        let passed = Path::new(&sibling(self.qf, name)).exists();
+        self.trace
+            .check(format!("'{}' next to the file", name), passed)
+    }
+
+    /// Record a decoder run and wrap its result
+    pub fn restore<'a>(
+        &mut self,
+        vendor: &'a str,
+        res: Result<Vec<Vec<u8>>>,
+    ) -> Result<UnQuarantine<'a>> {
+        self.restore_meta(
+            vendor,
+            res.map(|s| s.into_iter().map(Restored::new).collect()),
+        )
+    }
+
+    /// Record a run of a metadata aware decoder and wrap its result
+    pub fn restore_meta<'a>(
+        &mut self,
+        vendor: &'a str,
+        res: Result<Vec<Restored>>,
+    ) -> Result<UnQuarantine<'a>> {
+        self.trace.attempt(vendor, &res);
+        match res {
+            Ok(restored) => Ok(UnQuarantine::new(vendor, restored)),
+            Err(e) => self.take_candidate().ok_or(e),
+        }
+    }
+
+    /// Record a match on weak evidence (e.g. the extension alone); it is
+    /// kept aside and only returned when no stronger match follows
+    pub fn candidate(&mut self, vendor: &'static str, res: Result<Vec<Vec<u8>>>) {
+        self.trace.attempt(vendor, &res);
+        if let (Ok(restored), None) = (res, &self.candidate) {
+            self.trace
+                .check(format!("{} kept as a low confidence match", vendor), true);
+            let mut found =
+                UnQuarantine::new(vendor, restored.into_iter().map(Restored::new).collect());
+            found.confidence = Confidence::Low;
+            self.candidate = Some(found);
+        }
+    }
+
+    /// The low confidence match, if any
+    pub fn take_candidate(&mut self) -> Option<UnQuarantine<'static>> {
+        self.candidate.take()
+    }
+
+    pub fn trace(&mut self) -> &mut Trace {
+        self.trace
+    }
+}
+
+/// Printable magic as text, anything else as hex
+fn describe(bytes: &[u8]) -> String {
+    if bytes.iter().all(|b| b.is_ascii_graphic() || *b == b' ') {
+        format!("'{}'", String::from_utf8_lossy(bytes))
+    } else {
+        bytes
+            .iter()
+            .map(|b| format!("{:02X}", b))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
@@ -1,7 +1,7 @@
-use crate::Result;
+use crate::{error::Error, Result};
 use std::{convert::TryInto, fs::File, io::{BufReader, Read}, path::Path, vec};
 use crypto::blowfish::Blowfish;
-use crypto::symmetriccipher::BlockDecryptor;
+use crypto::symmetriccipher::{BlockDecryptor, BlockEncryptor};
 
 pub fn read_file<P: AsRef<Path>>(file: P) -> Result<Vec<u8>> {
     let f = File::open(file)?;
@@ -13,18 +13,57 @@ pub fn read_file<P: AsRef<Path>>(file: P) -> Result<Vec<u8>> {
     Ok(buffer)
 }
 
+/// `data[offset..offset + len]`, or a `LengthOutOfRange` error naming `field`
+pub fn get_slice<'a>(
+    data: &'a [u8],
+    offset: usize,
+    len: usize,
+    field: &'static str,
+) -> Result<&'a [u8]> {
+    offset
+        .checked_add(len)
+        .and_then(|end| data.get(offset..end))
+        .ok_or(Error::LengthOutOfRange {
+            field,
+            offset,
+            value: len as u64,
+            file_len: data.len(),
+        })
+}
+
+/// True for the path separators of both Windows and unix, so paths from
+/// either platform are split the same way
+fn is_separator(c: char) -> bool {
+    c == '/' || c == '\\'
+}
+
+/// The last component of a path
+pub fn file_name(path: &str) -> &str {
+    path.rsplit(is_separator).next().unwrap_or(path)
+}
+
+/// The folders of a path, innermost first
+pub fn parent_dirs(path: &str) -> impl Iterator<Item = &str> {
+    path.rsplit(is_separator).skip(1).filter(|d| !d.is_empty())
+}
+
+/// The path of `name` in the same folder as `path`
+pub fn sibling(path: &str, name: &str) -> String {
+    format!("{}{}", &path[..path.len() - file_name(path).len()], name)
+}
+
 pub fn unpack_i64(data: &[u8]) -> Result<i64> {
-    let vec: &[u8; 8] = data[..8].try_into()?;
+    let vec: &[u8; 8] = get_slice(data, 0, 8, "i64")?.try_into()?;
     Ok(i64::from_le_bytes(*vec))
 }
 
 pub fn unpack_i32(data: &[u8]) -> Result<i32> {
-    let vec: &[u8; 4] = data[..4].try_into()?;
+    let vec: &[u8; 4] = get_slice(data, 0, 4, "i32")?.try_into()?;
     Ok(i32::from_le_bytes(*vec))
 }
 
 pub fn unpack_i16(data: &[u8]) -> Result<i16> {
-    let vec: &[u8; 2] = data[..2].try_into()?;
+    let vec: &[u8; 2] = get_slice(data, 0, 2, "i16")?.try_into()?;
     Ok(i16::from_le_bytes(*vec))
 }
 
@@ -37,9 +76,33 @@ pub fn bytearray_xor(mut data: Vec<u8>, key: u8) -> Vec<u8> {
 
 pub fn blowfishit(_data: &[u8], _key: &[u8]) -> Result<Vec<u8>> {
     let state = Blowfish::new(_key);
-    let mut output = Vec::new();
-    state.decrypt_block(_data, &mut output[..]);
-    Ok(output.to_vec())
+    let mut output = vec![0u8; _data.len() - _data.len() % 8];
+    for (block, out) in _data.chunks_exact(8).zip(output.chunks_exact_mut(8)) {
+        state.decrypt_block(block, out);
+    }
+    Ok(output)
+}
+
+/// Blowfish ECB encryption; the input is zero padded to the block size
+pub fn blowfish_encrypt(data: &[u8], key: &[u8]) -> Vec<u8> {
+    let state = Blowfish::new(key);
+    let mut input = data.to_vec();
+    input.extend(vec![0u8; (8 - data.len() % 8) % 8]);
+    let mut output = vec![0u8; input.len()];
+    for (block, out) in input.chunks_exact(8).zip(output.chunks_exact_mut(8)) {
+        state.encrypt_block(block, out);
+    }
+    output
+}
+
+pub fn rc4_ksa(key: &[u8]) -> Vec<u8> {
+    let mut sbox: Vec<u8> = (0..=255).collect();
+    let mut j = 0_usize;
+    for i in 0..256 {
+        j = (j + sbox[i] as usize + key[i % key.len()] as usize) % 256;
+        sbox.swap(i, j);
+    }
+    sbox
 }
 
 pub fn rc4_decrypt(sbox: &mut Vec<u8>, data: &mut Vec<u8>) -> Vec<u8> {
@@ -55,3 +118,179 @@ pub fn rc4_decrypt(sbox: &mut Vec<u8>, data: &mut Vec<u8>) -> Vec<u8> {
     }
     out
 }
+
+/// Raw deflate stream made of stored (uncompressed) blocks
+pub fn deflate_stored(data: &[u8]) -> Vec<u8> {
+    let mut out = vec![];
+    let mut chunks = data.chunks(0xFFFF).peekable();
+    if chunks.peek().is_none() {
+        return vec![0x01, 0x00, 0x00, 0xFF, 0xFF];
+    }
+    while let Some(chunk) = chunks.next() {
+        out.push(if chunks.peek().is_none() { 0x01 } else { 0x00 });
+        out.extend((chunk.len() as u16).to_le_bytes());
+        out.extend((!(chunk.len() as u16)).to_le_bytes());
+        out.extend(chunk);
+    }
+    out
+}
+
+pub fn crc32(data: &[u8]) -> u32 {
+    crc::Crc::<u32>::new(&crc::CRC_32_ISO_HDLC).checksum(data)
+}
+
+fn crc32_update(crc: u32, byte: u8) -> u32 {
+    let mut crc = crc ^ byte as u32;
+    for _ in 0..8 {
+        crc = if crc & 1 == 1 {
+            (crc >> 1) ^ 0xEDB88320
+        } else {
+            crc >> 1
+        };
+    }
+    crc
+}
+
+/// Traditional PKWARE (ZipCrypto) stream cipher
+struct ZipCrypto {
+    keys: [u32; 3],
+}
+
+impl ZipCrypto {
+    fn new(password: &[u8]) -> Self {
+        let mut state = Self {
+            keys: [0x12345678, 0x23456789, 0x34567890],
+        };
+        for &b in password {
+            state.update(b);
+        }
+        state
+    }
+
+    fn update(&mut self, b: u8) {
+        self.keys[0] = crc32_update(self.keys[0], b);
+        self.keys[1] = self.keys[1]
+            .wrapping_add(self.keys[0] & 0xFF)
+            .wrapping_mul(134775813)
+            .wrapping_add(1);
+        self.keys[2] = crc32_update(self.keys[2], (self.keys[1] >> 24) as u8);
+    }
+
+    fn encrypt(&mut self, b: u8) -> u8 {
+        let temp = (self.keys[2] | 2) & 0xFFFF;
+        let c = b ^ ((temp.wrapping_mul(temp ^ 1) >> 8) & 0xFF) as u8;
+        self.update(b);
+        c
+    }
+}
+
+/// Zip archive with stored members, optionally ZipCrypto encrypted
+pub fn zip_stored(members: &[(&str, &[u8])], password: Option<&[u8]>) -> Vec<u8> {
+    let mut out = vec![];
+    let mut central = vec![];
+    let flags: u16 = if password.is_some() { 1 } else { 0 };
+    for (name, data) in members {
+        let crc = crc32(data);
+        let body = match password {
+            Some(password) => {
+                let mut cipher = ZipCrypto::new(password);
+                let mut header = [0u8; 12];
+                for (i, b) in header.iter_mut().enumerate() {
+                    *b = (i as u8).wrapping_mul(0x3D);
+                }
+                header[11] = (crc >> 24) as u8;
+                header
+                    .iter()
+                    .chain(data.iter())
+                    .map(|&b| cipher.encrypt(b))
+                    .collect()
+            }
+            None => data.to_vec(),
+        };
+        let offset = out.len() as u32;
+        let mut common = vec![];
+        common.extend(20u16.to_le_bytes()); // version needed
+        common.extend(flags.to_le_bytes());
+        common.extend(0u16.to_le_bytes()); // stored
+        common.extend(0u16.to_le_bytes()); // time
+        common.extend(0x21u16.to_le_bytes()); // date: 1980-01-01
+        common.extend(crc.to_le_bytes());
+        common.extend((body.len() as u32).to_le_bytes());
+        common.extend((data.len() as u32).to_le_bytes());
+        common.extend((name.len() as u16).to_le_bytes());
+        common.extend(0u16.to_le_bytes()); // extra field length
+
+        out.extend(0x04034B50u32.to_le_bytes());
+        out.extend(&common);
+        out.extend(name.as_bytes());
+        out.extend(body);
+
+        central.extend(0x02014B50u32.to_le_bytes());
+        central.extend(20u16.to_le_bytes()); // version made by
+        central.extend(&common);
+        central.extend([0u8; 6]); // comment length, disk, internal attributes
+        central.extend(0u32.to_le_bytes()); // external attributes
+        central.extend(offset.to_le_bytes());
+        central.extend(name.as_bytes());
+    }
+    let cd_offset = out.len() as u32;
+    out.extend(&central);
+    out.extend(0x06054B50u32.to_le_bytes());
+    out.extend([0u8; 4]);
+    out.extend((members.len() as u16).to_le_bytes());
+    out.extend((members.len() as u16).to_le_bytes());
+    out.extend((central.len() as u32).to_le_bytes());
+    out.extend(cd_offset.to_le_bytes());
+    out.extend(0u16.to_le_bytes());
+    out
+}
+
+pub fn to_utf16le(s: &str) -> Vec<u8> {
+    s.encode_utf16().flat_map(|c| c.to_le_bytes()).collect()
+}
+
+/// Decode UTF-16LE text, dropping trailing NULs
+pub fn from_utf16le(data: &[u8]) -> String {
+    let units: Vec<u16> = data
+        .chunks_exact(2)
+        .map(|c| u16::from_le_bytes([c[0], c[1]]))
+        .collect();
+    String::from_utf16_lossy(&units)
+        .trim_end_matches('\0')
+        .to_string()
+}
+
+/// Seconds between 1601-01-01 (FILETIME epoch) and 1970-01-01
+const FILETIME_UNIX_DIFF: u64 = 11_644_473_600;
+
+/// Windows FILETIME (100ns ticks since 1601) to seconds since the unix epoch
+pub fn filetime_to_unix(filetime: u64) -> u64 {
+    (filetime / 10_000_000).saturating_sub(FILETIME_UNIX_DIFF)
+}
+
+/// Seconds since the unix epoch to a Windows FILETIME
+pub fn unix_to_filetime(secs: u64) -> u64 {
+    (secs + FILETIME_UNIX_DIFF) * 10_000_000
+}
+
+/// Calendar date and time (UTC) to seconds since the unix epoch
+pub fn civil_to_unix(
+    year: u64,
+    month: u64,
+    day: u64,
+    hour: u64,
+    min: u64,
+    sec: u64,
+) -> Option<u64> {
+    if year < 1970 || !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+    // days since 1970-01-01, counting years from March so leap days come last
+    let (y, m) = if month > 2 {
+        (year, month - 3)
+    } else {
+        (year - 1, month + 9)
+    };
+    let days = 365 * y + y / 4 - y / 100 + y / 400 + (153 * m + 2) / 5 + day - 1 - 719_468;
+    Some(days * 86400 + hour * 3600 + min * 60 + sec)
+}
@@ -1,20 +1,50 @@
-use crate::{utils::unpack_i32, Result};
+use crate::{
+    error::Error,
+    metadata::Metadata,
+    utils::{get_slice, unpack_i32},
+    Result,
+};
 
-pub fn unquarantine(data: &[u8]) -> Result<Vec<Vec<u8>>> {
-    let o2d = unpack_i32(&data[58..])? + 0x58;
-    let data = &data[o2d as usize..data.len() - o2d as usize];
-    let key = vec![
+lazy_static! {
+    static ref KEY: Vec<u8> = vec![
         0x76, 0x33, 0x62, 0x61, 0x63, 0x6B, 0x75, 0x70, 0x21, 0x40, 0x23, 0x24, 0x25, 0x5E, 0x26,
         0x29,
     ];
+}
+
+pub fn unquarantine(data: &[u8]) -> Result<Vec<Vec<u8>>> {
+    let o2d =
+        (unpack_i32(get_slice(data, 58, 4, "data offset")?)? as u32 as usize).saturating_add(0x58);
+    // the payload is followed by as many bytes as the header is long
+    let data = data
+        .len()
+        .checked_sub(o2d)
+        .and_then(|end| data.get(o2d..end))
+        .ok_or(Error::LengthOutOfRange {
+            field: "data offset",
+            offset: 58,
+            value: o2d as u64,
+            file_len: data.len(),
+        })?;
     let mut dec = vec![];
     let mut ki = 0_usize;
     for i in 0..data.len() {
-        dec.push(data[i as usize] ^ key[ki]);
+        dec.push(data[i as usize] ^ KEY[ki]);
         ki += 1;
-        if ki > key.len() - 1 {
+        if ki > KEY.len() - 1 {
             ki = 0;
         }
     }
     Ok(vec![dec])
 }
+
+/// Wrap a payload into an AhnLab V3B file
+pub fn quarantine(payload: &[u8], _meta: &Metadata) -> Result<Vec<u8>> {
+    let mut header = vec![0u8; 0x58];
+    header[..16].copy_from_slice(b"AhnLab Inc. 2006");
+    let mut out = header.clone();
+    out.extend(payload.iter().enumerate().map(|(i, b)| b ^ KEY[i % KEY.len()]));
+    // the payload is followed by as many bytes as the header is long
+    out.extend(vec![0u8; header.len()]);
+    Ok(out)
+}
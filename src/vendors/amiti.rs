@@ -1,4 +1,4 @@
-use crate::{utils::rc4_decrypt, Result};
+use crate::{metadata::Metadata, utils::rc4_decrypt, Result};
 
 /// Amiti (IFC)
 pub fn unquarantine(data: &[u8]) -> Result<Vec<Vec<u8>>> {
@@ -10,6 +10,11 @@ pub fn unquarantine(data: &[u8]) -> Result<Vec<Vec<u8>>> {
     Ok(vec![rc4_decrypt(&mut ksa(), &mut data.to_owned())])
 }
 
+/// Wrap a payload into an Amiti IFC file
+pub fn quarantine(payload: &[u8], _meta: &Metadata) -> Result<Vec<u8>> {
+    Ok(rc4_decrypt(&mut ksa(), &mut payload.to_owned()))
+}
+
 fn ksa() -> Vec<u8> {
     let key = b"AA79e10d15l6o2t8";
     let mut sbox: Vec<u8> = (0..=255).collect();
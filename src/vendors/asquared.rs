@@ -1,25 +1,50 @@
 use crate::{
-    utils::{rc4_decrypt, unpack_i32},
+    metadata::Metadata,
+    utils::{get_slice, rc4_decrypt, to_utf16le, unpack_i32},
     Result,
 };
 use md5::Digest;
 
 /// ASquared (EQF)
 pub fn unquarantine(data: &[u8]) -> Result<Vec<Vec<u8>>> {
-    let data = &data[0x1A..];
-    let fno = unpack_i32(&data[0x14..])? as usize;
-    let fnl = unpack_i32(&data[0x18..])? as usize;
-    let mut fnn = data[fno..fno + fnl].to_vec();
+    let data = get_slice(data, 0x1A, data.len().saturating_sub(0x1A), "header")?;
+    let fno = unpack_i32(get_slice(data, 0x14, 4, "file name offset")?)? as u32 as usize;
+    let fnl = unpack_i32(get_slice(data, 0x18, 4, "file name length")?)? as u32 as usize;
+    let mut fnn = get_slice(data, fno, fnl, "file name")?.to_vec();
     fnn.push(0);
-    let dn = unpack_i32(&data[0x1C..])? as usize;
-    let mut tn = data[dn + 32..dn + 32 + 256].to_vec();
+    let dn = unpack_i32(get_slice(data, 0x1C, 4, "threat name offset")?)? as u32 as usize;
+    let mut tn = get_slice(data, dn.saturating_add(32), 256, "threat name")?.to_vec();
     tn.push(0);
-    let doo = unpack_i32(&data[0x24..])? as usize;
-    let data = &data[doo..];
+    let doo = unpack_i32(get_slice(data, 0x24, 4, "data offset")?)? as u32 as usize;
+    let data = get_slice(data, doo, data.len().saturating_sub(doo), "data offset")?;
     let newdata = rc4_decrypt(&mut ksa(), &mut data.to_vec());
     Ok(vec![newdata])
 }
 
+/// Wrap a payload (with its original file name and threat name) into an ASquared EQF file
+pub fn quarantine(payload: &[u8], meta: &Metadata) -> Result<Vec<u8>> {
+    let fnn = to_utf16le(meta.original_path.as_deref().unwrap_or_default());
+    let mut tn = meta.threat_name.clone().unwrap_or_default().into_bytes();
+    tn.resize(256, 0);
+
+    let fno = 0x28;
+    let dn = fno + fnn.len();
+    let doo = dn + 32 + 256;
+    let mut inner = vec![0u8; fno];
+    inner[0x14..0x18].copy_from_slice(&(fno as u32).to_le_bytes());
+    inner[0x18..0x1C].copy_from_slice(&(fnn.len() as u32).to_le_bytes());
+    inner[0x1C..0x20].copy_from_slice(&(dn as u32).to_le_bytes());
+    inner[0x24..0x28].copy_from_slice(&(doo as u32).to_le_bytes());
+    inner.extend(fnn);
+    inner.extend(vec![0u8; 32]);
+    inner.extend(tn);
+    inner.extend(rc4_decrypt(&mut ksa(), &mut payload.to_vec()));
+
+    let mut out = vec![0u8; 0x1A];
+    out.extend(inner);
+    Ok(out)
+}
+
 fn ksa() -> Vec<u8> {
     let mut hasher = md5::Md5::new();
     hasher.update(b"{A4A1BFF9-301A-40D3-86D3-D1F29E413B28}");
@@ -1,4 +1,4 @@
-use crate::Result;
+use crate::{error::Error, metadata::Metadata, Result};
 
 lazy_static! {
     static ref KEY: Vec<u8> = vec![
@@ -347,9 +347,26 @@ lazy_static! {
     ];
 }
 
+/// Avast/AVG chest files
 pub fn unquarantine(data: &[u8]) -> Result<Vec<Vec<u8>>> {
-    let data = &data[8..];
+    if !data.starts_with(b"-chest- ") {
+        return Err(Error::BadMagic {
+            vendor: "Avast/AVG chest",
+            expected: b"-chest- ".to_vec(),
+            found: data.iter().take(8).copied().collect(),
+        });
+    }
+    Ok(vec![xor_chest(&data[8..])])
+}
+
+/// Wrap a payload into an Avast/AVG chest file
+pub fn quarantine(payload: &[u8], _meta: &Metadata) -> Result<Vec<u8>> {
+    let mut out = b"-chest- ".to_vec();
+    out.extend(xor_chest(payload));
+    Ok(out)
+}
 
+fn xor_chest(data: &[u8]) -> Vec<u8> {
     let mut dec = vec![];
     let mut ki = 0;
     for i in 0..data.len() {
@@ -359,5 +376,5 @@ pub fn unquarantine(data: &[u8]) -> Result<Vec<Vec<u8>>> {
             ki = 0;
         }
     }
-    Ok(vec![dec])
+    dec
 }
@@ -1,11 +1,36 @@
 use crate::{
-    utils::{bytearray_xor, unpack_i32},
+    error::Error,
+    metadata::Metadata,
+    utils::{bytearray_xor, get_slice, unpack_i32},
     Result,
 };
 
 /// "Avira QUA Files
 pub fn unquarantine(data: &[u8]) -> Result<Vec<Vec<u8>>> {
-    let o2d = unpack_i32(&data[16..])? as usize;
+    let o2d = data_offset(data)?;
     let newdata = bytearray_xor(data[o2d..].to_vec(), 170);
     Ok(vec![newdata])
 }
+
+/// The offset of the payload, checked against the file length
+fn data_offset(data: &[u8]) -> Result<usize> {
+    let o2d = unpack_i32(get_slice(data, 16, 4, "data offset")?)? as u32 as usize;
+    if o2d < 20 || o2d > data.len() {
+        return Err(Error::LengthOutOfRange {
+            field: "data offset",
+            offset: 16,
+            value: o2d as u64,
+            file_len: data.len(),
+        });
+    }
+    Ok(o2d)
+}
+
+/// Wrap a payload into an Avira QUA file
+pub fn quarantine(payload: &[u8], _meta: &Metadata) -> Result<Vec<u8>> {
+    let mut out = vec![0u8; 0x34];
+    out[..11].copy_from_slice(b"AntiVir Qua");
+    out[16..20].copy_from_slice(&0x34u32.to_le_bytes());
+    out.extend(bytearray_xor(payload.to_vec(), 170));
+    Ok(out)
+}
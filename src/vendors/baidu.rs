@@ -1,8 +1,10 @@
 use crate::{
     error::Error,
-    utils::{unpack_i16, unpack_i32},
+    metadata::Metadata,
+    utils::{crc32, deflate_stored, get_slice, unpack_i16, unpack_i32},
     Result,
 };
+use md5::Digest;
 
 lazy_static! {
     static ref KEY: Vec<u8> = vec![
@@ -14,56 +16,37 @@ lazy_static! {
 /// Baidu QV Files
 pub fn unquarantine(data: &[u8]) -> Result<Vec<Vec<u8>>> {
     let _magic = unpack_i32(data)?;
-    let _time1 = unpack_i32(&data[4..])?;
-    let _task = unpack_i32(&data[8..])?;
-    let _scanstat = unpack_i32(&data[0xC..])?;
-    let mut md5 = data[0x10..0x10 + 0x42].to_vec();
-    md5.push(0);
+    let _time1 = unpack_i32(get_slice(data, 4, 4, "time")?)?;
+    let _task = unpack_i32(get_slice(data, 8, 4, "task")?)?;
+    let _scanstat = unpack_i32(get_slice(data, 0xC, 4, "scan status")?)?;
+    let _md5 = get_slice(data, 0x10, 0x42, "md5")?;
 
-    let data = &data[0x52..];
-    let leng = unpack_i32(data)? as usize;
-    if leng > data.len() {
-        return Err(Error::CannotUnQuarantineFile("baidu".to_string()));
-    }
-    let mut path = data[4..4 + leng].to_vec();
-    path.push(0);
-
-    let data = &data[4 + leng..];
-    let leng = unpack_i32(data)? as usize;
-    let mut clientid = data[4..4 + leng].to_vec();
-    clientid.push(0);
-
-    let data = &data[4 + leng..];
-    let _st = unpack_i32(data)? as usize;
-    let data = &data[4..];
-    let leng = unpack_i32(data)? as usize;
-    let mut threat = data[4..4 + leng].to_vec();
-    threat.push(0);
-
-    let data = &data[4 + leng..];
-    let leng = unpack_i32(data)? as usize;
-    let mut maltype = data[4..4 + leng].to_vec();
-    maltype.push(0);
-
-    let data = &data[4 + leng..];
-    let leng = unpack_i32(data)? as usize;
-    let mut packtype = data[4..4 + leng].to_vec();
-    packtype.push(0);
+    let mut pos = 0x52;
+    let _path = read_field(data, &mut pos, "path")?;
+    let _clientid = read_field(data, &mut pos, "client id")?;
+    let _st = get_slice(data, pos, 4, "status")?;
+    pos += 4;
+    let _threat = read_field(data, &mut pos, "threat name")?;
+    let _maltype = read_field(data, &mut pos, "malware type")?;
+    let _packtype = read_field(data, &mut pos, "pack type")?;
+    let _reserved = read_field(data, &mut pos, "reserved")?;
+    let _crc32 = get_slice(data, pos, 4, "crc32")?;
 
-    let data = &data[4 + leng..];
-    let leng = unpack_i32(data)? as usize;
-    let mut reserved = data[4..4 + leng].to_vec();
-    reserved.push(0);
-
-    let data = &data[4 + leng..];
-    let _crc32 = unpack_i32(data)? as usize;
-
-    let mut data = &data[4..];
+    let file_len = data.len();
+    let mut data = &data[pos + 4..];
     let mut dec = vec![];
     while !data.is_empty() {
-        let lend = unpack_i16(data)? as usize;
+        let lend = unpack_i16(data)? as u16 as usize;
         data = &data[2..];
         let dec2 = inflate::inflate_bytes(data).map_err(Error::InflateError)?;
+        if dec2.is_empty() || lend > data.len() {
+            return Err(Error::LengthOutOfRange {
+                field: "chunk length",
+                offset: file_len - data.len() - 2,
+                value: lend as u64,
+                file_len,
+            });
+        }
         let mut b = dec2[0];
         for i in 0..dec2.len() {
             b ^= KEY[i % KEY.len()];
@@ -74,3 +57,49 @@ pub fn unquarantine(data: &[u8]) -> Result<Vec<Vec<u8>>> {
     }
     Ok(vec![dec])
 }
+
+/// Wrap a payload (with its original path and threat name) into a Baidu QV file
+pub fn quarantine(payload: &[u8], meta: &Metadata) -> Result<Vec<u8>> {
+    let mut out = vec![];
+    out.extend(0u32.to_le_bytes()); // magic
+    out.extend((meta.quarantine_time.unwrap_or_default() as u32).to_le_bytes());
+    out.extend(0u32.to_le_bytes()); // task
+    out.extend(0u32.to_le_bytes()); // scan status
+    let mut md5 = format!("{:x}", md5::Md5::digest(payload)).into_bytes();
+    md5.resize(0x42, 0);
+    out.extend(md5);
+
+    push_field(&mut out, meta.original_path.as_deref().unwrap_or_default());
+    push_field(&mut out, ""); // client id
+    out.extend(0u32.to_le_bytes()); // scan status
+    push_field(&mut out, meta.threat_name.as_deref().unwrap_or_default());
+    push_field(&mut out, ""); // malware type
+    push_field(&mut out, ""); // pack type
+    push_field(&mut out, ""); // reserved
+    out.extend(crc32(payload).to_le_bytes());
+
+    for chunk in payload.chunks(0x1000) {
+        let mut chunk = chunk.to_vec();
+        // the first byte of every chunk is XORed with the key stream over the chunk length
+        for i in 0..chunk.len() {
+            chunk[0] ^= KEY[i % KEY.len()];
+        }
+        let deflated = deflate_stored(&chunk);
+        out.extend((deflated.len() as u16).to_le_bytes());
+        out.extend(deflated);
+    }
+    Ok(out)
+}
+
+/// Read a length prefixed field at `pos` and move `pos` past it
+fn read_field<'a>(data: &'a [u8], pos: &mut usize, field: &'static str) -> Result<&'a [u8]> {
+    let len = unpack_i32(get_slice(data, *pos, 4, field)?)? as u32 as usize;
+    let value = get_slice(data, *pos + 4, len, field)?;
+    *pos += 4 + len;
+    Ok(value)
+}
+
+fn push_field(out: &mut Vec<u8>, field: &str) {
+    out.extend((field.len() as u32).to_le_bytes());
+    out.extend(field.as_bytes());
+}
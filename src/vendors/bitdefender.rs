@@ -1,4 +1,4 @@
-use crate::Result;
+use crate::{metadata::Metadata, Result};
 
 /// BitDefender, Lavasoft AdAware, Total Defence BDQ Files
 pub fn unquarantine(data: &[u8]) -> Result<Vec<Vec<u8>>> {
@@ -6,9 +6,22 @@ pub fn unquarantine(data: &[u8]) -> Result<Vec<Vec<u8>>> {
     let mut cl: u8 = 25;
     let mut dl: u8 = 43;
     for i in 0..data.len() {
-        dec.push((data[i] as i8 - dl as i8) as u8 ^ cl);
+        dec.push(data[i].wrapping_sub(dl) ^ cl);
         cl = (cl as u16 + 3) as u8;
         dl = (dl as u16 + 20) as u8;
     }
     Ok(vec![dec])
 }
+
+/// Wrap a payload into a BitDefender BDQ file
+pub fn quarantine(payload: &[u8], _meta: &Metadata) -> Result<Vec<u8>> {
+    let mut enc = vec![];
+    let mut cl: u8 = 25;
+    let mut dl: u8 = 43;
+    for b in payload {
+        enc.push((b ^ cl).wrapping_add(dl));
+        cl = cl.wrapping_add(3);
+        dl = dl.wrapping_add(20);
+    }
+    Ok(enc)
+}
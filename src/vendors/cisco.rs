@@ -1,4 +1,4 @@
-use crate::Result;
+use crate::{metadata::Metadata, Result};
 
 /// Cisco AMP
 pub fn amp_unquarantine(data: &[u8]) -> Result<Vec<Vec<u8>>> {
@@ -8,3 +8,8 @@ pub fn amp_unquarantine(data: &[u8]) -> Result<Vec<Vec<u8>>> {
     }
     Ok(vec![dec])
 }
+
+/// Wrap a payload into a Cisco AMP quarantine file
+pub fn amp_quarantine(payload: &[u8], _meta: &Metadata) -> Result<Vec<u8>> {
+    Ok(amp_unquarantine(payload)?.remove(0))
+}
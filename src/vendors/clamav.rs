@@ -0,0 +1,121 @@
+use crate::{
+    error::Error,
+    metadata::{Metadata, Restored},
+    utils::civil_to_unix,
+    Result,
+};
+use md5::Digest;
+use std::{fs, path::Path, time::UNIX_EPOCH};
+
+/// ClamTk's list of quarantined files, next to its `viruses` folder
+pub const RESTORE_FILE: &str = "restore";
+/// ClamTk's scan logs, next to its `viruses` folder
+pub const HISTORY_DIR: &str = "history";
+
+/// ClamAV (`clamscan --move/--copy`) and ClamTk keep plain copies of the
+/// quarantined files, the data is returned as is.
+pub fn unquarantine(data: &[u8]) -> Result<Vec<Vec<u8>>> {
+    Ok(vec![data.to_vec()])
+}
+
+/// ClamTk quarantine (`~/.clamtk/viruses` or `~/.local/share/clamtk/viruses`).
+///
+/// Layout as written by ClamTk (<https://github.com/dave-theunsub/clamtk>):
+/// the files are plain copies; the `restore` file, one level up, holds a
+/// `md5:original path:mode` line for each of them, which is matched against
+/// the md5 of `data`. The threat name comes from the `path: threat FOUND`
+/// lines of the daily scan logs in `history` (`Jan-01-2022.log`), the most
+/// recent log first.
+pub fn clamtk_restore(qf: &str, data: &[u8]) -> Result<Restored> {
+    let root = Path::new(qf)
+        .parent()
+        .and_then(Path::parent)
+        .unwrap_or_else(|| Path::new(""));
+    let restore_file = root.join(RESTORE_FILE);
+    if !restore_file.exists() {
+        return Err(Error::MissingCompanionFile {
+            vendor: "ClamTk",
+            file: restore_file.display().to_string(),
+        });
+    }
+    let md5 = format!("{:x}", md5::Md5::digest(data));
+    let restore = String::from_utf8_lossy(&fs::read(&restore_file)?).into_owned();
+    let (path, mode) = restore
+        .lines()
+        .filter_map(|line| {
+            let (hash, rest) = line.split_once(':')?;
+            let (path, mode) = rest.rsplit_once(':')?;
+            hash.eq_ignore_ascii_case(&md5).then_some((path, mode))
+        })
+        .next()
+        .ok_or_else(|| Error::ChecksumMismatch {
+            vendor: "ClamTk",
+            expected: format!("an entry of {}", restore_file.display()),
+            found: md5.clone(),
+        })?;
+
+    let mut metadata = Metadata {
+        original_path: Some(path.to_string()),
+        threat_name: history_threat(&root.join(HISTORY_DIR), path),
+        ..Default::default()
+    };
+    metadata.extra.insert("md5".to_string(), md5);
+    metadata.extra.insert("mode".to_string(), mode.to_string());
+    Ok(Restored {
+        data: data.to_vec(),
+        metadata,
+    })
+}
+
+/// The detection of `path` in the most recent ClamTk scan log mentioning it
+fn history_threat(dir: &Path, path: &str) -> Option<String> {
+    let mut logs: Vec<_> = fs::read_dir(dir)
+        .ok()?
+        .filter_map(|entry| Some(entry.ok()?.path()))
+        .filter(|log| log.extension().unwrap_or_default() == "log")
+        .collect();
+    logs.sort_by_key(|log| log_date(log).or_else(|| modified(log)));
+    logs.iter().rev().find_map(|log| {
+        let text = String::from_utf8_lossy(&fs::read(log).ok()?).into_owned();
+        text.lines().find_map(|line| {
+            let (file, threat) = line.strip_suffix(" FOUND")?.rsplit_once(": ")?;
+            (file == path).then(|| threat.to_string())
+        })
+    })
+}
+
+/// The day a ClamTk scan log is named after, e.g. `Jan-01-2022.log`
+fn log_date(log: &Path) -> Option<u64> {
+    const MONTHS: [&str; 12] = [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ];
+    let mut parts = log.file_stem()?.to_str()?.splitn(3, '-');
+    let month = parts.next()?;
+    let month = MONTHS.iter().position(|m| m.eq_ignore_ascii_case(month))? as u64 + 1;
+    let day = parts.next()?.parse().ok()?;
+    let year = parts.next()?.parse().ok()?;
+    civil_to_unix(year, month, day, 0, 0, 0)
+}
+
+fn modified(log: &Path) -> Option<u64> {
+    let time = fs::metadata(log).ok()?.modified().ok()?;
+    Some(time.duration_since(UNIX_EPOCH).ok()?.as_secs())
+}
+
+/// ClamTk `restore` file line for a quarantined payload
+pub fn clamtk_restore_entry(payload: &[u8], meta: &Metadata) -> String {
+    format!(
+        "{:x}:{}:0644\n",
+        md5::Md5::digest(payload),
+        meta.original_path.as_deref().unwrap_or_default()
+    )
+}
+
+/// ClamTk scan log line reporting the detection of a quarantined file
+pub fn clamtk_history_entry(meta: &Metadata) -> String {
+    format!(
+        "{}: {} FOUND\n",
+        meta.original_path.as_deref().unwrap_or_default(),
+        meta.threat_name.as_deref().unwrap_or_default()
+    )
+}
@@ -1,29 +1,33 @@
 use crate::{
-    utils::{bytearray_xor, unpack_i16, unpack_i32},
+    metadata::Metadata,
+    utils::{bytearray_xor, crc32, get_slice, unpack_i16, unpack_i32, zip_stored},
     Result,
 };
 use std::io::{copy, BufReader, Cursor};
+use md5::Digest;
 use zip::ZipArchive;
 
 /// CMC Antivirus (CMC)
 pub fn unquarantine(data: &[u8]) -> Result<Vec<Vec<u8>>> {
+    // every fixed field is within the 0x200 byte header
+    get_slice(data, 0, 0x200, "header")?;
     let _magic = &data[..32];
     let _ffv = unpack_i32(&data[0x20..])?;
     let _crc = unpack_i32(&data[0x28..])?;
     let _adler = unpack_i32(&data[0x2C..])?;
-    let ofn = unpack_i16(&data[0x50..])? as usize;
+    let ofn = unpack_i16(&data[0x50..])? as u16 as usize;
     let _us = unpack_i32(&data[0x54..])?;
     let _qs = unpack_i32(&data[0x58..])?;
-    let tnl = unpack_i16(&data[0x6C..])? as usize;
+    let tnl = unpack_i16(&data[0x6C..])? as u16 as usize;
 
-    let _fnn = &data[0x200..0x200 + ofn];
-    let _tn = &data[0x200 + ofn..0x200 + ofn + tnl];
+    let _fnn = get_slice(data, 0x200, ofn, "file name")?;
+    let _tn = get_slice(data, 0x200 + ofn, tnl, "threat name")?;
     let _md5 = &data[0x30..0x30 + 16];
     let _submitid = &data[0x40..0x40 + 16];
 
     let data = &data[0x200 + ofn + tnl..];
-    let buflen = unpack_i32(data)? as usize;
-    let data = &data[4..4 + buflen];
+    let buflen = unpack_i32(data)? as u32 as usize;
+    let data = get_slice(data, 4, buflen, "buffer length")?;
     let _meta_dec = bytearray_xor(data.to_vec(), 30);
     let mut dec = vec![];
     let mut zip = ZipArchive::new(BufReader::new(Cursor::new(data.to_vec())))?;
@@ -37,3 +41,26 @@ pub fn unquarantine(data: &[u8]) -> Result<Vec<Vec<u8>>> {
     }
     Ok(dec)
 }
+
+/// Wrap a payload (with its original file name and threat name) into a CMC file
+pub fn quarantine(payload: &[u8], meta: &Metadata) -> Result<Vec<u8>> {
+    let fnn = meta.original_path.clone().unwrap_or_default();
+    let tn = meta.threat_name.clone().unwrap_or_default();
+    let name = fnn.rsplit(['/', '\\']).next().unwrap_or_default();
+    let zipped = zip_stored(&[(if name.is_empty() { "file" } else { name }, payload)], None);
+
+    let mut out = vec![0u8; 0x200];
+    out[..23].copy_from_slice(b"CMC Quarantined Malware");
+    out[0x20..0x24].copy_from_slice(&1u32.to_le_bytes());
+    out[0x28..0x2C].copy_from_slice(&crc32(payload).to_le_bytes());
+    out[0x30..0x40].copy_from_slice(&md5::Md5::digest(payload));
+    out[0x50..0x52].copy_from_slice(&(fnn.len() as u16).to_le_bytes());
+    out[0x54..0x58].copy_from_slice(&(payload.len() as u32).to_le_bytes());
+    out[0x58..0x5C].copy_from_slice(&(zipped.len() as u32).to_le_bytes());
+    out[0x6C..0x6E].copy_from_slice(&(tn.len() as u16).to_le_bytes());
+    out.extend(fnn.as_bytes());
+    out.extend(tn.as_bytes());
+    out.extend((zipped.len() as u32).to_le_bytes());
+    out.extend(zipped);
+    Ok(out)
+}
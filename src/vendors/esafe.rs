@@ -1,7 +1,12 @@
-use crate::Result;
+use crate::{metadata::Metadata, Result};
 
 /// ESafe (VIR)
 pub fn unquarantine(data: &[u8]) -> Result<Vec<Vec<u8>>> {
     let bytes = base64::decode(data)?;
     Ok(vec![bytes])
 }
+
+/// Wrap a payload into an ESafe VIR file
+pub fn quarantine(payload: &[u8], _meta: &Metadata) -> Result<Vec<u8>> {
+    Ok(base64::encode(payload).into_bytes())
+}
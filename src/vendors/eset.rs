@@ -1,10 +1,15 @@
-use crate::Result;
+use crate::{metadata::Metadata, Result};
 
 /// ESET (NQF)
 pub fn unquarantine(data: &[u8]) -> Result<Vec<Vec<u8>>> {
     let mut newdata = vec![];
     for i in 0..data.len() {
-        newdata.push((data[i] as i8 - 84) as u8 ^ 0xa5);
+        newdata.push(data[i].wrapping_sub(84) ^ 0xa5);
     }
     Ok(vec![newdata])
 }
+
+/// Wrap a payload into an ESET NQF file
+pub fn quarantine(payload: &[u8], _meta: &Metadata) -> Result<Vec<u8>> {
+    Ok(payload.iter().map(|b| (b ^ 0xA5).wrapping_add(84)).collect())
+}
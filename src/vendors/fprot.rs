@@ -1,4 +1,8 @@
-use crate::{utils::unpack_i32, Result};
+use crate::{
+    metadata::Metadata,
+    utils::{get_slice, unpack_i32},
+    Result,
+};
 
 lazy_static! {
     static ref FLT: Vec<u8> = vec![
@@ -24,11 +28,24 @@ lazy_static! {
 }
 
 pub fn unquarantine(data: &[u8]) -> Result<Vec<Vec<u8>>> {
-    let o2d = unpack_i32(&data[4..])? + 0xDC;
-    let data = &data[o2d as usize..];
+    let o2d =
+        (unpack_i32(get_slice(data, 4, 4, "data offset")?)? as u32 as usize).saturating_add(0xDC);
+    let data = get_slice(data, o2d, data.len().saturating_sub(o2d), "data offset")?;
     let mut newdata = vec![];
     for i in 0..data.len() {
         newdata.push(FLT[data[i as usize] as usize]);
     }
     Ok(vec![newdata])
 }
+
+/// Wrap a payload into an F-Prot TMP file
+pub fn quarantine(payload: &[u8], _meta: &Metadata) -> Result<Vec<u8>> {
+    let mut inverse = [0u8; 256];
+    for (i, b) in FLT.iter().enumerate() {
+        inverse[*b as usize] = i as u8;
+    }
+    let mut out = vec![0u8; 0xDC];
+    out[..3].copy_from_slice(b"KSS");
+    out.extend(payload.iter().map(|b| inverse[*b as usize]));
+    Ok(out)
+}
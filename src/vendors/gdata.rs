@@ -1,6 +1,7 @@
 use crate::{
     error::Error,
-    utils::{rc4_decrypt, unpack_i32},
+    metadata::Metadata,
+    utils::{get_slice, rc4_decrypt, unpack_i32},
     Result,
 };
 
@@ -11,27 +12,65 @@ lazy_static! {
     ];
 }
 
+const HEADER_MAGIC: [u8; 4] = [0xCA, 0xFE, 0xBA, 0xBE];
+const BODY_MAGIC: [u8; 4] = [0xBA, 0xAD, 0xF0, 0x0D];
+
 /// G-Data (Q) (Magic@0=0xCAFEBABE)
+///
+/// The header and body blocks are skipped: their layout is not documented,
+/// so no metadata is read from them.
 pub fn unquarantine(data: &[u8]) -> Result<Vec<Vec<u8>>> {
-    let size = data.len();
-    let hdr_len = unpack_i32(&data[4..])? as usize;
-    if hdr_len > data.len() {
-        return Err(Error::CannotUnQuarantineFile("gdata".to_string()));
+    let (_header, _body, payload_offset) = blocks(data)?;
+    let newdata = rc4_decrypt(&mut ksa(), &mut data[payload_offset..].to_vec());
+    Ok(vec![newdata])
+}
+
+/// True when the file has the G-Data layout: a `CAFEBABE` header block
+/// followed by a `BAADF00D` body block, both within the file
+pub fn is_gdata(data: &[u8]) -> bool {
+    data.starts_with(&HEADER_MAGIC) && blocks(data).is_ok()
+}
+
+/// The (still encrypted) header and body blocks, and where the payload starts
+fn blocks(data: &[u8]) -> Result<(&[u8], &[u8], usize)> {
+    if !data.starts_with(&HEADER_MAGIC) {
+        return Err(Error::BadMagic {
+            vendor: "G-Data Q",
+            expected: HEADER_MAGIC.to_vec(),
+            found: data.iter().take(4).copied().collect(),
+        });
     }
-    let _hdr = &data[8..8 + hdr_len as usize];
-    let data = &data[8 + hdr_len as usize..];
-    if data[..4] != vec![0xBA, 0xAD, 0xF0, 0x0D] {
-        return Err(Error::CannotUnQuarantineFile("gdata".to_string()));
+    let hdr_len = unpack_i32(get_slice(data, 4, 4, "header length")?)? as u32 as usize;
+    let header = get_slice(data, 8, hdr_len, "header")?;
+    let body_offset = 8 + hdr_len;
+    let rest = &data[body_offset..];
+    if !rest.starts_with(&BODY_MAGIC) {
+        return Err(Error::BadMagic {
+            vendor: "G-Data Q",
+            expected: BODY_MAGIC.to_vec(),
+            found: rest.iter().take(4).copied().collect(),
+        });
     }
-    let body_len = unpack_i32(&data[4..])? as usize;
-    if body_len > size {
-        return Err(Error::CannotUnQuarantineFile("gdata".to_string()));
+    let body_len = unpack_i32(get_slice(rest, 4, 4, "body length")?)? as u32 as usize;
+    if 8 + body_len > rest.len() {
+        return Err(Error::LengthOutOfRange {
+            field: "body length",
+            offset: body_offset + 4,
+            value: body_len as u64,
+            file_len: data.len(),
+        });
     }
-    let _body = &data[8..8 + body_len];
-    let data = &data[8 + body_len as usize..];
+    Ok((header, &rest[8..8 + body_len], body_offset + 8 + body_len))
+}
 
-    let newdata = rc4_decrypt(&mut ksa(), &mut data.to_vec());
-    Ok(vec![newdata])
+/// Wrap a payload into a G-Data Q file, with empty header and body blocks
+pub fn quarantine(payload: &[u8], _meta: &Metadata) -> Result<Vec<u8>> {
+    let mut out = HEADER_MAGIC.to_vec();
+    out.extend(0u32.to_le_bytes());
+    out.extend(BODY_MAGIC);
+    out.extend(0u32.to_le_bytes());
+    out.extend(rc4_decrypt(&mut ksa(), &mut payload.to_vec()));
+    Ok(out)
 }
 
 fn ksa() -> Vec<u8> {
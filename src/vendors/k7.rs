@@ -1,15 +1,54 @@
 use crate::{
     error::Error,
-    utils::{bytearray_xor, unpack_i32},
+    metadata::{Metadata, Restored},
+    utils::{bytearray_xor, file_name, get_slice, unpack_i32},
     Result,
 };
+use md5::Digest;
 
 /// K7 Antivirus (<md5>.QNT)
 pub fn unquarantine(data: &[u8]) -> Result<Vec<Vec<u8>>> {
-    let len = unpack_i32(&data[0x128..])? as usize;
-    if len > data.len() {
-        return Err(Error::CannotUnQuarantineFile("k7".to_string()));
+    let len = unpack_i32(get_slice(data, 0x128, 4, "payload length")?)? as u32 as usize;
+    if 0x178 + len > data.len() {
+        return Err(Error::LengthOutOfRange {
+            field: "payload length",
+            offset: 0x128,
+            value: len as u64,
+            file_len: data.len(),
+        });
     }
     let newdata = bytearray_xor(data[0x178..0x178 + len].to_vec(), 0xFF);
     Ok(vec![newdata])
 }
+
+/// K7 QNT files, with the payload checked against the MD5 the file is
+/// named after, when it is
+pub fn restore(qf: &str, data: &[u8]) -> Result<Restored> {
+    let payload = unquarantine(data)?.remove(0);
+    let mut metadata = Metadata::default();
+    let name = file_name(qf);
+    let stem = name.rsplit_once('.').map_or(name, |(stem, _)| stem);
+    if stem.len() == 32 && stem.chars().all(|c| c.is_ascii_hexdigit()) {
+        let found = format!("{:x}", md5::Md5::digest(&payload));
+        if !stem.eq_ignore_ascii_case(&found) {
+            return Err(Error::ChecksumMismatch {
+                vendor: "K7",
+                expected: stem.to_ascii_lowercase(),
+                found,
+            });
+        }
+        metadata.extra.insert("md5".to_string(), found);
+    }
+    Ok(Restored {
+        data: payload,
+        metadata,
+    })
+}
+
+/// Wrap a payload into a K7 QNT file
+pub fn quarantine(payload: &[u8], _meta: &Metadata) -> Result<Vec<u8>> {
+    let mut out = vec![0u8; 0x178];
+    out[0x128..0x12C].copy_from_slice(&(payload.len() as u32).to_le_bytes());
+    out.extend(bytearray_xor(payload.to_vec(), 0xFF));
+    Ok(out)
+}
@@ -1,4 +1,10 @@
-use crate::{error::Error, utils::unpack_i32, Result};
+use crate::{
+    error::Error,
+    metadata::{Metadata, Restored},
+    utils::{file_name, get_slice, to_utf16le, unpack_i32},
+    Result,
+};
+use md5::Digest;
 
 lazy_static! {
     static ref KLQ_KEY: Vec<u8> = vec![0xE2, 0x45, 0x48, 0xEC, 0x69, 0x0E, 0x5C, 0xAC];
@@ -8,35 +14,53 @@ lazy_static! {
 /// Kaspersky KLQ files
 pub fn av_unquarantine(data: &[u8]) -> Result<Vec<Vec<u8>>> {
     let mut data = data.to_owned();
-    let magic = unpack_i32(&data)?;
-    if magic != 0x42514C4B {
-        return Err(Error::CannotUnQuarantineFile("kav".to_string()));
+    if !data.starts_with(b"KLQB") {
+        return Err(Error::BadMagic {
+            vendor: "Kaspersky KLQ",
+            expected: b"KLQB".to_vec(),
+            found: data.iter().take(4).copied().collect(),
+        });
     }
     let fsize = data.len();
 
-    let headerlen = unpack_i32(&data[8..])?;
-    let metaoffset = unpack_i32(&data[0x10..])?;
-    let metalen = unpack_i32(&data[0x20..])?;
-    let origlen = unpack_i32(&data[0x30..])?;
+    let headerlen = unpack_i32(get_slice(&data, 8, 4, "header length")?)?;
+    let metaoffset = unpack_i32(get_slice(&data, 0x10, 4, "metadata offset")?)?;
+    let metalen = unpack_i32(get_slice(&data, 0x20, 4, "metadata length")?)?;
+    let origlen = unpack_i32(get_slice(&data, 0x30, 4, "original length")?)?;
 
-    if fsize < (headerlen + origlen + metalen) as usize {
-        return Err(Error::CannotUnQuarantineFile("kav".to_string()));
+    if headerlen < 0
+        || origlen < 0
+        || metalen < 0
+        || fsize < headerlen as usize + origlen as usize + metalen as usize
+    {
+        return Err(Error::LengthOutOfRange {
+            field: "original length",
+            offset: 0x30,
+            value: origlen as u64,
+            file_len: fsize,
+        });
     }
-    if metaoffset < headerlen + origlen {
-        return Err(Error::CannotUnQuarantineFile("kav".to_string()));
+    if metaoffset < 0 || (metaoffset as usize) < headerlen as usize + origlen as usize {
+        return Err(Error::LengthOutOfRange {
+            field: "metadata offset",
+            offset: 0x10,
+            value: metaoffset as u64,
+            file_len: fsize,
+        });
     }
 
     let mut curoffset = metaoffset as usize;
-    let mut length = unpack_i32(&data[curoffset..])?;
+    let mut length = unpack_i32(get_slice(&data, curoffset, 4, "metadata chunk length")?)?;
     while length > 0 {
+        get_slice(&data, curoffset + 4, length as usize, "metadata chunk")?;
         for i in 0..length {
             data[curoffset + 4 + i as usize] ^= KLQ_KEY[(i % KLQ_KEY.len() as i32) as usize];
         }
         curoffset += (4 + length) as usize;
-        if curoffset >= (metaoffset + metalen) as usize {
+        if curoffset >= metaoffset as usize + metalen as usize {
             break;
         }
-        length = unpack_i32(&data[curoffset..])?;
+        length = unpack_i32(get_slice(&data, curoffset, 4, "metadata chunk length")?)?;
     }
     for i in 0..origlen {
         data[(headerlen + i) as usize] ^= KLQ_KEY[(i % KLQ_KEY.len() as i32) as usize];
@@ -46,6 +70,38 @@ pub fn av_unquarantine(data: &[u8]) -> Result<Vec<Vec<u8>>> {
         .to_vec()])
 }
 
+/// Wrap a payload (with its original path) into a Kaspersky KLQ file
+pub fn av_quarantine(payload: &[u8], meta: &Metadata) -> Result<Vec<u8>> {
+    let headerlen = 0x40_usize;
+    let mut meta_chunks = vec![];
+    if let Some(path) = &meta.original_path {
+        let path = to_utf16le(path);
+        meta_chunks.extend((path.len() as u32).to_le_bytes());
+        meta_chunks.extend(
+            path.iter()
+                .enumerate()
+                .map(|(i, b)| b ^ KLQ_KEY[i % KLQ_KEY.len()]),
+        );
+    }
+    // a zero length chunk terminates the metadata
+    meta_chunks.extend(0u32.to_le_bytes());
+
+    let mut out = vec![0u8; headerlen];
+    out[..4].copy_from_slice(&0x42514C4Bu32.to_le_bytes());
+    out[8..12].copy_from_slice(&(headerlen as u32).to_le_bytes());
+    out[0x10..0x14].copy_from_slice(&((headerlen + payload.len()) as u32).to_le_bytes());
+    out[0x20..0x24].copy_from_slice(&(meta_chunks.len() as u32).to_le_bytes());
+    out[0x30..0x34].copy_from_slice(&(payload.len() as u32).to_le_bytes());
+    out.extend(
+        payload
+            .iter()
+            .enumerate()
+            .map(|(i, b)| b ^ KLQ_KEY[i % KLQ_KEY.len()]),
+    );
+    out.extend(meta_chunks);
+    Ok(out)
+}
+
 /// Kaspersky (System Watcher's <md5>.bin)
 pub fn system_watcher_unquarantine(data: &[u8]) -> Result<Vec<Vec<u8>>> {
     let mut newdata = vec![];
@@ -54,3 +110,31 @@ pub fn system_watcher_unquarantine(data: &[u8]) -> Result<Vec<Vec<u8>>> {
     }
     Ok(vec![newdata])
 }
+
+/// Wrap a payload into a Kaspersky System Watcher file
+pub fn system_watcher_quarantine(payload: &[u8], _meta: &Metadata) -> Result<Vec<u8>> {
+    Ok(system_watcher_unquarantine(payload)?.remove(0))
+}
+
+/// Kaspersky System Watcher files, checked against the MD5 they are named
+/// after so that other `.bin` files are not claimed.
+///
+/// The SQLite `quarantine.db` of Kaspersky Endpoint Security and its object
+/// blobs are not read: there is no documented schema or key to decode them
+/// from.
+pub fn system_watcher_restore(qf: &str, data: &[u8]) -> Result<Restored> {
+    let payload = system_watcher_unquarantine(data)?.remove(0);
+    let name = file_name(qf);
+    let expected = name.split('.').next().unwrap_or(name).to_ascii_lowercase();
+    let found = format!("{:x}", md5::Md5::digest(&payload));
+    if expected != found {
+        return Err(Error::ChecksumMismatch {
+            vendor: "Kaspersky System Watcher",
+            expected,
+            found,
+        });
+    }
+    let mut restored = Restored::new(payload);
+    restored.metadata.extra.insert("md5".to_string(), found);
+    Ok(restored)
+}
@@ -1,8 +1,21 @@
-use crate::{error::Error, Result};
+use crate::{
+    error::Error,
+    metadata::Metadata,
+    utils::{deflate_stored, get_slice},
+    Result,
+};
 
 /// Lumension LEMSS (lqf)
 pub fn unquarantine(data: &[u8]) -> Result<Vec<Vec<u8>>> {
     // WANT_GZIP
-    let dec2 = inflate::inflate_bytes(&data[32..]).map_err(Error::InflateError)?;
+    let data = get_slice(data, 32, data.len().saturating_sub(32), "header")?;
+    let dec2 = inflate::inflate_bytes(data).map_err(Error::InflateError)?;
     Ok(vec![dec2])
 }
+
+/// Wrap a payload into a Lumension LEMSS file
+pub fn quarantine(payload: &[u8], _meta: &Metadata) -> Result<Vec<u8>> {
+    let mut out = vec![0u8; 32];
+    out.extend(deflate_stored(payload));
+    Ok(out)
+}
@@ -1,5 +1,10 @@
-use crate::{utils::rc4_decrypt, Result};
+use crate::{
+    metadata::{Metadata, Restored},
+    utils::{rc4_decrypt, read_file},
+    Result,
+};
 use md5::Digest;
+use std::path::Path;
 
 /// MalwareBytes Data & Quarantine files (QUAR)
 pub fn unquarantine(data: &[u8]) -> Result<Vec<Vec<u8>>> {
@@ -9,6 +14,91 @@ pub fn unquarantine(data: &[u8]) -> Result<Vec<Vec<u8>>> {
     Ok(vec![outdata])
 }
 
+/// MalwareBytes QUAR files paired with their DATA record.
+/// `qf` may name either file of the pair; the payload comes from the
+/// `.quar` file and the metadata from the `.data` file next to it.
+/// The record is read on a best effort basis: a `.data` file that does not
+/// hold a JSON record, or has no `.quar` file next to it, is returned
+/// decrypted, and a `.quar` file is restored whatever its record holds.
+pub fn restore(qf: &str, data: &[u8]) -> Result<Vec<Restored>> {
+    let path = Path::new(qf);
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    match extension.as_str() {
+        "data" => {
+            let record = unquarantine(data)?.remove(0);
+            let quar = path.with_extension("quar");
+            match parse_record(&record) {
+                Ok(metadata) if quar.exists() => Ok(vec![Restored {
+                    data: unquarantine(&read_file(quar)?)?.remove(0),
+                    metadata,
+                }]),
+                Ok(metadata) => Ok(vec![Restored {
+                    data: record,
+                    metadata,
+                }]),
+                Err(_) => Ok(vec![Restored::new(record)]),
+            }
+        }
+        "quar" => {
+            let mut restored = Restored::new(unquarantine(data)?.remove(0));
+            let record = path.with_extension("data");
+            if let Some(metadata) = read_file(record)
+                .ok()
+                .and_then(|record| parse_data(&record).ok())
+            {
+                restored.metadata = metadata;
+            }
+            Ok(vec![restored])
+        }
+        _ => Ok(unquarantine(data)?.into_iter().map(Restored::new).collect()),
+    }
+}
+
+/// Decrypt a `.data` record, a JSON object.
+///
+/// The field names are not documented, so every field is kept as is in
+/// the `extra` metadata rather than guessed into the known fields.
+pub fn parse_data(data: &[u8]) -> Result<Metadata> {
+    parse_record(&unquarantine(data)?.remove(0))
+}
+
+fn parse_record(record: &[u8]) -> Result<Metadata> {
+    let text = String::from_utf8(record.to_vec())?;
+    let text = text.trim_start_matches('\u{feff}').trim();
+    let record: serde_json::Map<String, serde_json::Value> = serde_json::from_str(text)?;
+    let mut metadata = Metadata::default();
+    for (key, value) in record {
+        let value = match value {
+            serde_json::Value::String(s) => s,
+            serde_json::Value::Null => continue,
+            other => other.to_string(),
+        };
+        metadata.extra.insert(key, value);
+    }
+    Ok(metadata)
+}
+
+/// Wrap a payload into a MalwareBytes QUAR file
+pub fn quarantine(payload: &[u8], _meta: &Metadata) -> Result<Vec<u8>> {
+    Ok(rc4_decrypt(&mut ksa(), &mut payload.to_owned()))
+}
+
+/// Build a `.data` record holding the given fields
+pub fn data_quarantine(fields: &[(&str, &str)]) -> Result<Vec<u8>> {
+    let mut text = serde_json::to_string(
+        &fields
+            .iter()
+            .map(|(k, v)| (k.to_string(), serde_json::Value::String(v.to_string())))
+            .collect::<serde_json::Map<_, _>>(),
+    )?
+    .into_bytes();
+    Ok(rc4_decrypt(&mut ksa(), &mut text))
+}
+
 fn ksa() -> Vec<u8> {
     let mut hasher = md5::Md5::new();
     hasher.update(b"XBXM8362QIXD9+637HCB02/VN0JF6Z3)cB9UFZMdF3I.*c.,c5SbO7)WNZ8CY1(XMUDb");
@@ -1,24 +1,137 @@
-use crate::Result;
+use crate::{
+    metadata::{Metadata, Restored},
+    utils::civil_to_unix,
+    Result,
+};
 use ole::OleFile;
+use std::collections::BTreeMap;
+
+/// Sections of an INI file, each a list of `key=value` pairs
+pub type Ini = BTreeMap<String, BTreeMap<String, String>>;
 
 /// McAfee Quarantine files (BUP) /full support for OLE format/
 pub fn unquarantine(file: &str) -> Result<Vec<Vec<u8>>> {
+    Ok(restore(file)?.into_iter().map(|r| r.data).collect())
+}
+
+/// McAfee BUP files with the `Details` stream parsed into per-file metadata.
+/// Every `File_N` stream is matched with the `[File_N]` section of `Details`.
+pub fn restore(file: &str) -> Result<Vec<Restored>> {
     let res = OleFile::from_file_blocking(file)?;
+    let details = decrypt_bup_string(res.open_stream(&["Details"])?);
+    let mut entries = details_metadata(&details);
     let mut response = Vec::new();
     for stream_name in res.list_streams() {
-        // Read File
+        if stream_name.eq_ignore_ascii_case("Details") {
+            continue;
+        }
         let data = res.open_stream(&[stream_name.as_str()])?;
-        let file_data = decrypt_bup_bytes(data);
-        response.push(file_data);
+        let metadata = match entries.remove(&stream_name) {
+            Some(metadata) => metadata,
+            None => entries.get("Details").cloned().unwrap_or_default(),
+        };
+        response.push(Restored {
+            data: decrypt_bup_bytes(data),
+            metadata,
+        });
     }
-    
-    // Read File String
-    let data = res.open_stream(&["Details"])?;
-    let file_str = decrypt_bup_string(data);
-    response.push(file_str.as_bytes().to_vec());
     Ok(response)
 }
 
+/// Parse the decoded `Details` stream into metadata keyed by section name.
+/// `[File_N]` entries also carry the detection from `[Details]`.
+pub fn details_metadata(details: &str) -> BTreeMap<String, Metadata> {
+    let ini = parse_ini(details);
+    let common = ini
+        .get("Details")
+        .map(|section| section_metadata(section, "Time"))
+        .unwrap_or_default();
+    let mut entries = BTreeMap::new();
+    for (name, section) in &ini {
+        if name == "Details" {
+            continue;
+        }
+        let mut metadata = section_metadata(section, "Creation");
+        // the file times are kept as extras, the quarantine time is the detection time
+        if let Some(time) = metadata.quarantine_time.take() {
+            metadata
+                .extra
+                .insert("creation_time".to_string(), time.to_string());
+        }
+        for (prefix, key) in [
+            ("Modification", "modification_time"),
+            ("Access", "access_time"),
+        ] {
+            if let Some(time) = section_time(section, prefix) {
+                metadata.extra.insert(key.to_string(), time.to_string());
+            }
+        }
+        metadata.merge(&common);
+        entries.insert(name.clone(), metadata);
+    }
+    entries.insert("Details".to_string(), common);
+    entries
+}
+
+/// A small INI reader: `[section]` headers, `key=value` lines, `;` comments
+pub fn parse_ini(text: &str) -> Ini {
+    let mut ini = Ini::new();
+    let mut section = String::new();
+    for line in text.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with(';') || line.starts_with('#') {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            section = name.trim().to_string();
+            ini.entry(section.clone()).or_default();
+        } else if let Some((key, value)) = line.split_once('=') {
+            ini.entry(section.clone())
+                .or_default()
+                .insert(key.trim().to_string(), value.trim().to_string());
+        }
+    }
+    ini
+}
+
+/// Known fields of a section; `time` is the prefix of the
+/// `<time>Year`, `<time>Month`, ... keys holding its timestamp
+fn section_metadata(section: &BTreeMap<String, String>, time: &str) -> Metadata {
+    let mut metadata = Metadata {
+        quarantine_time: section_time(section, time),
+        ..Default::default()
+    };
+    for (key, value) in section {
+        match key.as_str() {
+            "OriginalName" => metadata.original_path = Some(value.clone()),
+            "DetectionName" => metadata.threat_name = Some(value.clone()),
+            k if k.starts_with("Creation")
+                || k.starts_with("Modification")
+                || k.starts_with("Access")
+                || (k.starts_with("Time") && k.len() > 4) => {}
+            _ => {
+                metadata.extra.insert(key.clone(), value.clone());
+            }
+        }
+    }
+    metadata
+}
+
+fn section_time(section: &BTreeMap<String, String>, prefix: &str) -> Option<u64> {
+    let field = |name: &str| -> Option<u64> {
+        section
+            .get(&format!("{}{}", prefix, name))
+            .and_then(|v| v.parse().ok())
+    };
+    civil_to_unix(
+        field("Year")?,
+        field("Month")?,
+        field("Day")?,
+        field("Hours").or_else(|| field("Hour")).unwrap_or(0),
+        field("Minutes").or_else(|| field("Minute")).unwrap_or(0),
+        field("Seconds").or_else(|| field("Second")).unwrap_or(0),
+    )
+}
+
 fn decrypt_bup_string(bup_data: Vec<u8>) -> String {
     bup_data.iter().map(|byte| (byte ^ 0x6A) as char).collect()
 }
@@ -1,9 +1,13 @@
 use crate::{
     error::Error,
-    utils::{bytearray_xor, rc4_decrypt},
+    metadata::{Metadata, Restored},
+    utils::{
+        bytearray_xor, file_name, filetime_to_unix, from_utf16le, get_slice, rc4_decrypt,
+        to_utf16le, unix_to_filetime, unpack_i32, unpack_i64,
+    },
     Result,
 };
-use std::convert::TryInto;
+use std::{convert::TryInto, fs, path::Path};
 
 lazy_static! {
     static ref KEY: Vec<i32> = vec![
@@ -32,23 +36,157 @@ lazy_static! {
 pub fn pc_unquarantine(data: &[u8]) -> Result<Vec<Vec<u8>>> {
     let mut data = data.to_owned();
     let fsize = data.len();
-    if fsize < 12 || data[0] != 0x0B || data[1] != 0xAD || data[2] != 0x00 {
-        return Err(Error::CannotUnQuarantineFile("mse".to_string()));
+    if !data.starts_with(&[0x0B, 0xAD, 0x00]) {
+        return Err(Error::BadMagic {
+            vendor: "Microsoft Windows Defender (PC)",
+            expected: vec![0x0B, 0xAD, 0x00],
+            found: data.iter().take(3).copied().collect(),
+        });
+    }
+    if fsize < 12 {
+        return Err(Error::LengthOutOfRange {
+            field: "header length",
+            offset: 8,
+            value: 4,
+            file_len: fsize,
+        });
     }
     let mut sbox = ksa();
     let outdata = rc4_decrypt(&mut sbox, &mut data);
 
     let headerlen_vec: &[u8; 4] = outdata[8..12].try_into()?;
-    let headerlen = 0x28 + i32::from_le_bytes(*headerlen_vec);
+    let headerlen = 0x28 + u32::from_le_bytes(*headerlen_vec) as usize;
     let origlen_vec: &[u8; 4] =
-        outdata[headerlen as usize - 12..headerlen as usize - 8].try_into()?;
-    let origlen = i32::from_le_bytes(*origlen_vec);
+        get_slice(&outdata, headerlen - 12, 4, "original length")?.try_into()?;
+    let origlen = u32::from_le_bytes(*origlen_vec) as usize;
 
-    if origlen + headerlen != fsize as i32 {
-        return Err(Error::CannotUnQuarantineFile("mse".to_string()));
+    if origlen + headerlen != fsize {
+        return Err(Error::LengthOutOfRange {
+            field: "original length",
+            offset: headerlen - 12,
+            value: origlen as u64,
+            file_len: fsize,
+        });
     }
 
-    Ok(vec![outdata[headerlen as usize..].to_vec()])
+    Ok(vec![outdata[headerlen..].to_vec()])
+}
+
+/// Microsoft Defender `ResourceData` blobs, Windows and Defender for Endpoint
+/// on Linux (mdatp) and macOS alike.
+///
+/// These keep the Windows layout, `Entries` and `ResourceData` folders under
+/// `/var/opt/microsoft/mdatp/quarantine` on Linux and
+/// `/Library/Application Support/Microsoft/Defender/quarantine` on macOS, so
+/// the blobs decode like Defender PC ones. The `Entries/{GUID}` records next
+/// to `ResourceData` are read best effort for the original path, detection
+/// name and time of the blob; a missing or unreadable record leaves the
+/// metadata empty.
+pub fn resource_restore(qf: &str, data: &[u8]) -> Result<Restored> {
+    let data = pc_unquarantine(data)?.remove(0);
+    let metadata = Path::new(qf)
+        .parent()
+        .and_then(Path::parent)
+        .filter(|dir| {
+            dir.file_name()
+                .is_some_and(|name| name.eq_ignore_ascii_case("ResourceData"))
+        })
+        .and_then(Path::parent)
+        .and_then(|root| entry_metadata(&root.join("Entries"), file_name(qf)))
+        .unwrap_or_default();
+    Ok(Restored { data, metadata })
+}
+
+/// The metadata of the `file` entry whose SHA1 is `sha1` in any of the
+/// records of `dir`
+fn entry_metadata(dir: &Path, sha1: &str) -> Option<Metadata> {
+    fs::read_dir(dir)
+        .ok()?
+        .filter_map(|entry| fs::read(entry.ok()?.path()).ok())
+        .find_map(|record| {
+            parse_entries(&record)
+                .ok()?
+                .into_iter()
+                .find(|(hash, _)| hash.eq_ignore_ascii_case(sha1))
+                .map(|(_, metadata)| metadata)
+        })
+}
+
+/// Decode a Defender `Entries/{GUID}` record into the SHA1 (uppercase hex,
+/// the `ResourceData` file name) and metadata of each of its `file` entries.
+///
+/// Layout as parsed by defender-dump (<https://github.com/knez/defender-dump>):
+/// three parts, each RC4 encrypted on its own with the Defender key. A 0x3C
+/// bytes header holds the lengths of the other two at 0x28 and 0x2C. The
+/// first has the quarantine FILETIME at 0x20 and the detection name from
+/// 0x34. The second has an entry count, then the offsets of the entries: a
+/// UTF-16LE path, 4 bytes, the entry type, padding to 4 bytes, 4 bytes and
+/// the SHA1 of the file.
+pub fn parse_entries(data: &[u8]) -> Result<Vec<(String, Metadata)>> {
+    let header = decrypt(get_slice(data, 0, 0x3C, "entries header")?);
+    let data1_len =
+        unpack_i32(get_slice(&header, 0x28, 4, "entries data1 length")?)? as u32 as usize;
+    let data2_len =
+        unpack_i32(get_slice(&header, 0x2C, 4, "entries data2 length")?)? as u32 as usize;
+    let data1 = decrypt(get_slice(data, 0x3C, data1_len, "entries data1")?);
+    let data2 = decrypt(get_slice(
+        data,
+        0x3C + data1_len,
+        data2_len,
+        "entries data2",
+    )?);
+
+    let filetime = unpack_i64(get_slice(&data1, 0x20, 8, "entries time")?)? as u64;
+    let detection = get_slice(&data1, 0x34, data1.len().saturating_sub(0x34), "detection")?;
+    let detection = detection.split(|&b| b == 0).next().unwrap_or_default();
+    let threat_name = String::from_utf8_lossy(detection).into_owned();
+
+    let count = unpack_i32(get_slice(&data2, 0, 4, "entries count")?)? as u32 as usize;
+    let mut entries = vec![];
+    for i in 0..count {
+        let offset = unpack_i32(get_slice(&data2, 4 + i * 4, 4, "entry offset")?)? as u32 as usize;
+        let entry = get_slice(&data2, offset, data2.len().saturating_sub(offset), "entry")?;
+        let (path, sha1, kind) = parse_entry(entry)?;
+        if kind != "file" {
+            continue;
+        }
+        let metadata = Metadata {
+            original_path: Some(path),
+            threat_name: Some(threat_name.clone()),
+            quarantine_time: Some(filetime_to_unix(filetime)),
+            ..Default::default()
+        };
+        entries.push((sha1, metadata));
+    }
+    Ok(entries)
+}
+
+/// The path, SHA1 and type of an entry of the second part of a record
+fn parse_entry(entry: &[u8]) -> Result<(String, String, String)> {
+    let mut pos = entry
+        .windows(3)
+        .position(|w| w == [0, 0, 0])
+        .ok_or(Error::LengthOutOfRange {
+            field: "entry path",
+            offset: 0,
+            value: entry.len() as u64,
+            file_len: entry.len(),
+        })?
+        + 1;
+    let path = from_utf16le(&entry[..pos]);
+    let path = path.strip_prefix(r"\\?\").unwrap_or(&path).to_string();
+    pos += 4;
+    let kind = get_slice(entry, pos, entry.len().saturating_sub(pos), "entry type")?;
+    let kind_len = kind.iter().position(|&b| b == 0).unwrap_or(kind.len());
+    let kind = String::from_utf8_lossy(&kind[..kind_len]).into_owned();
+    pos += kind_len + 1;
+    pos += (4 - pos % 4) % 4;
+    pos += 4;
+    let sha1 = get_slice(entry, pos, 20, "entry sha1")?
+        .iter()
+        .map(|b| format!("{:02X}", b))
+        .collect();
+    Ok((path, sha1, kind))
 }
 
 /// Microsoft Defender MAC
@@ -61,6 +199,66 @@ pub fn antimalware_unquarantine(data: &[u8]) -> Result<Vec<Vec<u8>>> {
     Ok(vec![bytearray_xor(data.to_owned(), 0xff)])
 }
 
+/// Wrap a payload into a Microsoft Defender PC (0B AD) blob
+pub fn pc_quarantine(payload: &[u8], _meta: &Metadata) -> Result<Vec<u8>> {
+    let headerlen = 0x38_usize;
+    let mut plain = vec![0u8; headerlen];
+    plain[8..12].copy_from_slice(&((headerlen - 0x28) as u32).to_le_bytes());
+    plain[headerlen - 12..headerlen - 8].copy_from_slice(&(payload.len() as u32).to_le_bytes());
+    plain.extend(payload);
+    let mut out = rc4_decrypt(&mut ksa(), &mut plain);
+    // the magic is checked on the encrypted data
+    out[..3].copy_from_slice(&[0x0B, 0xAD, 0x00]);
+    Ok(out)
+}
+
+/// Wrap a payload into a Microsoft Defender MAC file
+pub fn mac_quarantine(payload: &[u8], _meta: &Metadata) -> Result<Vec<u8>> {
+    Ok(bytearray_xor(payload.to_owned(), 0x25))
+}
+
+/// Wrap a payload into a Microsoft Antimalware / Microsoft Security Essentials file
+pub fn antimalware_quarantine(payload: &[u8], _meta: &Metadata) -> Result<Vec<u8>> {
+    Ok(bytearray_xor(payload.to_owned(), 0xff))
+}
+
+/// Wrap a quarantined file's details into a Microsoft Defender `Entries`
+/// record, with a single `file` entry for the `ResourceData` blob named `sha1`
+pub fn entries_quarantine(sha1: &[u8; 20], meta: &Metadata) -> Result<Vec<u8>> {
+    let mut data1 = vec![0u8; 0x34];
+    let filetime = unix_to_filetime(meta.quarantine_time.unwrap_or_default());
+    data1[0x20..0x28].copy_from_slice(&filetime.to_le_bytes());
+    data1.extend(meta.threat_name.as_deref().unwrap_or_default().as_bytes());
+    data1.push(0);
+
+    let mut data2 = vec![];
+    data2.extend(1u32.to_le_bytes());
+    data2.extend(8u32.to_le_bytes());
+    data2.extend(to_utf16le(
+        meta.original_path.as_deref().unwrap_or_default(),
+    ));
+    data2.extend([0, 0, 1, 0]);
+    data2.extend(b"file\0");
+    while (data2.len() - 8) % 4 != 0 {
+        data2.push(0);
+    }
+    data2.extend([0u8; 4]);
+    data2.extend(sha1);
+
+    let mut header = vec![0u8; 0x3C];
+    header[0x28..0x2C].copy_from_slice(&(data1.len() as u32).to_le_bytes());
+    header[0x2C..0x30].copy_from_slice(&(data2.len() as u32).to_le_bytes());
+    let mut out = decrypt(&header);
+    out.extend(decrypt(&data1));
+    out.extend(decrypt(&data2));
+    Ok(out)
+}
+
+/// RC4 with the Defender key; encrypting and decrypting are the same
+fn decrypt(data: &[u8]) -> Vec<u8> {
+    rc4_decrypt(&mut ksa(), &mut data.to_vec())
+}
+
 fn ksa() -> Vec<u8> {
     let mut sbox: Vec<u8> = (0..=255).collect();
     let mut j = 0_usize;
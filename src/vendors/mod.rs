@@ -1,3 +1,6 @@
+//! Vendor specific decoders (`*unquarantine`) and encoders (`*quarantine`).
+//! The encoders wrap a payload into the vendor's quarantine format, which is
+//! handy to build benign test fixtures.
 /// AhnLab (V3B) UnQuarantine
 pub mod ahnlab;
 /// Amiti (IFC)
@@ -16,6 +19,8 @@ pub mod bitdefender;
 pub mod bullguard;
 /// Cisco
 pub mod cisco;
+/// ClamAV / ClamTk (viruses folder + restore file)
+pub mod clamav;
 /// CMC Antivirus (CMC)
 pub mod cmc;
 /// ESafe (VIR)
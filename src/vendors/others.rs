@@ -1,4 +1,7 @@
-use crate::{utils::bytearray_xor, Result};
+use crate::{
+    utils::{bytearray_xor, zip_stored},
+    Result,
+};
 
 /// Zip Unquarantine:
 /// Total AV, SpyBOT
@@ -8,7 +11,10 @@ pub fn zip_unquarantine(data: &[u8], password: Option<&[u8]>) -> Result<Vec<Vec<
        zip::ZipArchive::new(std::io::BufReader::new(std::io::Cursor::new(data.to_vec())))?;
 
     for i in 0..zip.len() {
-        let mut file = zip.by_index_decrypt(i, password.unwrap())?.unwrap();
+        let mut file = match password {
+            Some(password) => zip.by_index_decrypt(i, password)??,
+            None => zip.by_index(i)?,
+        };
         let mut res: Vec<u8> = vec![];
         std::io::copy(&mut file, &mut res)?;
         ress.push(res);
@@ -16,6 +22,12 @@ pub fn zip_unquarantine(data: &[u8], password: Option<&[u8]>) -> Result<Vec<Vec<
     Ok(ress)
 }
 
+/// Zip Quarantine: wrap a payload into a (password protected) zip
+/// Total AV, SpyBOT
+pub fn zip_quarantine(payload: &[u8], password: Option<&[u8]>) -> Result<Vec<u8>> {
+    Ok(zip_stored(&[("file", payload)], password))
+}
+
 /// Data Unquarantine
 /// SUPERAntiSpyware, Symantec QBD and QBI Files
 pub fn data_unquarantine(data: &[u8], key: u8) -> Result<Vec<Vec<u8>>> {
@@ -23,9 +35,21 @@ pub fn data_unquarantine(data: &[u8], key: u8) -> Result<Vec<Vec<u8>>> {
     Ok(vec![newdata])
 }
 
+/// Data Quarantine
+/// SUPERAntiSpyware, Symantec QBD and QBI Files
+pub fn data_quarantine(payload: &[u8], key: u8) -> Result<Vec<u8>> {
+    Ok(bytearray_xor(payload.to_vec(), key))
+}
+
 /// XORFF
 ///
 pub fn xorff_unquarantine(data: &[u8]) -> Result<Vec<Vec<u8>>> {
     let qdata = bytearray_xor(data.to_owned(), 0xFF);
     Ok(vec![qdata])
 }
+
+/// XORFF
+/// Wrap a payload with a one byte XOR 0xFF
+pub fn xorff_quarantine(payload: &[u8]) -> Result<Vec<u8>> {
+    Ok(bytearray_xor(payload.to_owned(), 0xFF))
+}
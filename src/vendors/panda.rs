@@ -1,4 +1,9 @@
-use crate::{error::Error, utils::blowfishit, Result};
+use crate::{
+    error::Error,
+    metadata::Metadata,
+    utils::{blowfish_encrypt, blowfishit, deflate_stored, zip_stored},
+    Result,
+};
 use std::io::{copy, BufReader, Cursor};
 use zip::ZipArchive;
 
@@ -24,3 +29,9 @@ pub fn unquarantine(data: &[u8]) -> Result<Vec<Vec<u8>>> {
     }
     Ok(ress)
 }
+
+/// Wrap a payload into a Panda <GUID> Zip file
+pub fn quarantine(payload: &[u8], _meta: &Metadata) -> Result<Vec<u8>> {
+    let enc = blowfish_encrypt(&deflate_stored(payload), &KEY);
+    Ok(zip_stored(&[("file", &enc)], None))
+}
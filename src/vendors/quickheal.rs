@@ -1,4 +1,4 @@
-use crate::Result;
+use crate::{metadata::Metadata, Result};
 
 /// QuickHeal <hash> files
 pub fn unquarantine(data: &[u8]) -> Result<Vec<Vec<u8>>> {
@@ -10,3 +10,8 @@ pub fn unquarantine(data: &[u8]) -> Result<Vec<Vec<u8>>> {
     }
     Ok(vec![dec])
 }
+
+/// Wrap a payload into a QuickHeal file
+pub fn quarantine(payload: &[u8], _meta: &Metadata) -> Result<Vec<u8>> {
+    Ok(unquarantine(payload)?.remove(0))
+}
@@ -1,6 +1,11 @@
-use crate::{utils::bytearray_xor, Result};
+use crate::{metadata::Metadata, utils::bytearray_xor, Result};
 
 /// Sentinel One (MAL)
 pub fn unquarantine(data: &[u8]) -> Result<Vec<Vec<u8>>> {
     Ok(vec![bytearray_xor(data.to_owned(), 255)])
 }
+
+/// Wrap a payload into a Sentinel One MAL file
+pub fn quarantine(payload: &[u8], _meta: &Metadata) -> Result<Vec<u8>> {
+    Ok(bytearray_xor(payload.to_owned(), 255))
+}
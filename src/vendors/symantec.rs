@@ -1,10 +1,18 @@
 use crate::{
     error::Error,
-    utils::{blowfishit, bytearray_xor, unpack_i32, unpack_i64},
+    metadata::{Metadata, Restored},
+    utils::{
+        blowfish_encrypt, blowfishit, bytearray_xor, get_slice, unpack_i32, unpack_i64, zip_stored,
+    },
     vendors::others,
     Result,
 };
-use std::convert::TryInto;
+use md5::Digest;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    convert::TryInto,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
 
 /// Symantec Quarantine files (VBN), including from SEP on Linux
 pub fn ep_unquarantine(data: &[u8]) -> Result<Vec<Vec<u8>>> {
@@ -12,7 +20,11 @@ pub fn ep_unquarantine(data: &[u8]) -> Result<Vec<Vec<u8>>> {
     let filesize = qdata.len();
     let mut dataoffset = unpack_i32(&qdata)?;
     if dataoffset != 0x1290 {
-        return Err(Error::CannotUnQuarantineFile("sep".to_string()));
+        return Err(Error::BadMagic {
+            vendor: "Symantec VBN",
+            expected: 0x1290u32.to_le_bytes().to_vec(),
+            found: qdata[..4].to_vec(),
+        });
     }
     let mut data = bytearray_xor(qdata, 0x5A);
     dataoffset += 0x28;
@@ -85,49 +97,228 @@ pub fn ep_unquarantine(data: &[u8]) -> Result<Vec<Vec<u8>>> {
     Ok(vec![bindata])
 }
 
+/// Wrap a payload (with its original path) into a Symantec VBN file
+pub fn ep_quarantine(payload: &[u8], meta: &Metadata) -> Result<Vec<u8>> {
+    let mut data = vec![0u8; 0x1290 + 0x28];
+    let path = meta.original_path.clone().unwrap_or_default().into_bytes();
+    let path_len = path.len().min(0x180);
+    data[4..4 + path_len].copy_from_slice(&path[..path_len]);
+
+    // a code 9 tag with value 8 announces the XORed container that follows
+    data.push(9);
+    data.extend(8u32.to_le_bytes());
+    data.extend((payload.len() as u64).to_le_bytes());
+
+    let headerlen = 0x28_usize;
+    let mut container = vec![0u8; headerlen];
+    container[8..12].copy_from_slice(&(headerlen as u32).to_le_bytes());
+    container[headerlen - 12..headerlen - 8].copy_from_slice(&(payload.len() as u32).to_le_bytes());
+    container.extend(payload);
+    data.push(9);
+    data.extend((container.len() as u32).to_le_bytes());
+    data.extend(bytearray_xor(container, 0xFF));
+
+    let mut out = bytearray_xor(data, 0x5A);
+    // the data offset is stored in the clear
+    out[..4].copy_from_slice(&0x1290u32.to_le_bytes());
+    Ok(out)
+}
+
 /// Symantec ccSubSdk files: {GUID} files and submissions.idx
 pub fn cc_sub_sdk_unquarantine(data: &[u8]) -> Result<Vec<Vec<u8>>> {
-    Ok(vec![blowfishit(
-        &data[32..].to_vec(),
-        &data[16..32].to_vec(),
-    )?])
+    let key = get_slice(data, 16, 16, "key")?;
+    let body = get_slice(data, 32, data.len().saturating_sub(32), "data")?;
+    Ok(vec![blowfishit(body, key)?])
+}
+
+/// Wrap a payload into a Symantec ccSubSdk {GUID} file
+pub fn cc_sub_sdk_quarantine(payload: &[u8], _meta: &Metadata) -> Result<Vec<u8>> {
+    let key = md5::Md5::digest(payload);
+    let mut out = vec![0u8; 16];
+    out.extend(key.as_slice());
+    out.extend(blowfish_encrypt(payload, &key));
+    Ok(out)
 }
 
 /// Symantec Quarantine Index files (QBI)
 pub fn idx_unquarantine(data: &[u8]) -> Result<Vec<Vec<u8>>> {
-    let data = &data[0x30..];
+    let file_len = data.len();
+    let mut data = get_slice(data, 0x30, file_len.saturating_sub(0x30), "index header")?;
     let mut res = vec![];
-    while data[..4] == vec![0x40, 0x99, 0xC6, 0x89] {
-        let len1 = unpack_i32(&data[24..])? as usize;
+    while data.len() >= 56 && data.starts_with(&[0x40, 0x99, 0xC6, 0x89]) {
+        let len1 = unpack_i32(&data[24..])? as u32 as usize;
         let _len2 = unpack_i32(&data[28..])? as usize;
-        let dec = blowfishit(&data[56..56 + len1].to_vec(), &data[40..40 + 16].to_vec())?;
+        if 56 + len1 > data.len() {
+            return Err(Error::LengthOutOfRange {
+                field: "record length",
+                offset: file_len - data.len() + 24,
+                value: len1 as u64,
+                file_len,
+            });
+        }
+        let dec = blowfishit(&data[56..56 + len1], &data[40..40 + 16])?;
         res.push(dec);
+        data = &data[56 + len1..];
     }
     Ok(res)
 }
 
+/// Wrap a payload into a Symantec ccSubSdk submissions.idx file
+pub fn idx_quarantine(payload: &[u8], _meta: &Metadata) -> Result<Vec<u8>> {
+    let key = md5::Md5::digest(payload);
+    let enc = blowfish_encrypt(payload, &key);
+    let mut out = vec![0u8; 0x30];
+    let mut record = vec![0u8; 56];
+    record[..4].copy_from_slice(&[0x40, 0x99, 0xC6, 0x89]);
+    record[24..28].copy_from_slice(&(enc.len() as u32).to_le_bytes());
+    record[28..32].copy_from_slice(&(payload.len() as u32).to_le_bytes());
+    record[40..56].copy_from_slice(&key);
+    out.extend(record);
+    out.extend(enc);
+    Ok(out)
+}
+
 /// Symantec Quarantine Index files (QBD)
 pub fn qbd_unquarantine(data: &[u8]) -> Result<Vec<Vec<u8>>> {
     others::data_unquarantine(data, 0xB3)
 }
 
+/// Wrap a payload into a Symantec QBD file
+pub fn qbd_quarantine(payload: &[u8], _meta: &Metadata) -> Result<Vec<u8>> {
+    others::data_quarantine(payload, 0xB3)
+}
+
 /// Symantec Quarantine files on MAC (quarantine.qtn)
 pub fn qtn_unquarantine(data: &[u8]) -> Result<Vec<Vec<u8>>> {
-    let mut ress = vec![];
-    let mut zip =
-        zip::ZipArchive::new(std::io::BufReader::new(std::io::Cursor::new(data.to_vec())))?;
+    Ok(qtn_restore(data)?.into_iter().map(|r| r.data).collect())
+}
 
+/// Symantec quarantine.qtn on MAC with the property lists decoded.
+///
+/// Items may come with a property list (`*.plist`, XML or binary) describing
+/// them: either `<member>.plist` next to the member, or the only property
+/// list in the member's folder. A property list is only taken as metadata
+/// when it pairs with such a member and parses as a dictionary; any other one
+/// (a quarantined LaunchAgent for instance) is returned as a payload. Members
+/// without a property list are returned as they are, without metadata.
+pub fn qtn_restore(data: &[u8]) -> Result<Vec<Restored>> {
+    let mut zip = zip::ZipArchive::new(std::io::Cursor::new(data))?;
+    let mut members = vec![];
     for i in 0..zip.len() {
         let mut file = zip.by_index(i)?;
+        if file.is_dir() {
+            continue;
+        }
         let mut res: Vec<u8> = vec![];
         std::io::copy(&mut file, &mut res)?;
-        ress.push(res);
+        members.push((file.name().to_string(), res));
+    }
+
+    let plists: BTreeMap<_, _> = members
+        .iter()
+        .filter(|(name, _)| is_plist(name))
+        .filter_map(|(name, content)| {
+            let value = plist::Value::from_reader(std::io::Cursor::new(content)).ok()?;
+            Some((name.clone(), value.into_dictionary()?))
+        })
+        .collect();
+    let mut paired = BTreeSet::new();
+    let mut ress = vec![];
+    for (name, content) in &members {
+        if is_plist(name) {
+            continue;
+        }
+        let restored = match qtn_plist(&plists, name) {
+            Some((plist, dict)) => {
+                paired.insert(plist);
+                Restored {
+                    data: content.clone(),
+                    metadata: qtn_metadata(dict),
+                }
+            }
+            None => Restored::new(content.clone()),
+        };
+        ress.push(restored);
+    }
+    for (name, content) in members {
+        if is_plist(&name) && !paired.contains(&name) {
+            ress.push(Restored::new(content));
+        }
     }
     Ok(ress)
 }
 
+fn is_plist(name: &str) -> bool {
+    name.to_ascii_lowercase().ends_with(".plist")
+}
+
+/// The property list describing an archive member: `<member>.plist`, or the
+/// only property list in the member's folder
+fn qtn_plist<'a>(
+    plists: &'a BTreeMap<String, plist::Dictionary>,
+    name: &str,
+) -> Option<(&'a String, &'a plist::Dictionary)> {
+    if let Some(found) = plists.get_key_value(&format!("{}.plist", name)) {
+        return Some(found);
+    }
+    let folder = qtn_folder(name)?;
+    let mut in_folder = plists
+        .iter()
+        .filter(|(plist, _)| qtn_folder(plist) == Some(folder));
+    match (in_folder.next(), in_folder.next()) {
+        (Some(found), None) => Some(found),
+        _ => None,
+    }
+}
+
+/// The folder of an archive member, `None` at the top level
+fn qtn_folder(name: &str) -> Option<&str> {
+    name.rsplit_once('/').map(|(dir, _)| dir)
+}
+
+/// The scalar values of an item's property list, under their own keys; the
+/// format of the keys is not documented so none is mapped to the named fields
+fn qtn_metadata(dict: &plist::Dictionary) -> Metadata {
+    let mut metadata = Metadata::default();
+    for (name, value) in dict {
+        let value = match value {
+            plist::Value::String(s) => s.clone(),
+            plist::Value::Integer(i) => i.to_string(),
+            plist::Value::Boolean(b) => b.to_string(),
+            plist::Value::Date(date) => match SystemTime::from(*date).duration_since(UNIX_EPOCH) {
+                Ok(d) => d.as_secs().to_string(),
+                Err(_) => continue,
+            },
+            _ => continue,
+        };
+        metadata.extra.insert(name.clone(), value);
+    }
+    metadata
+}
+
+/// Wrap a payload into a Symantec quarantine.qtn file on MAC
+pub fn qtn_quarantine(payload: &[u8], meta: &Metadata) -> Result<Vec<u8>> {
+    let mut dict = plist::Dictionary::new();
+    if let Some(path) = &meta.original_path {
+        dict.insert("OriginalPath".to_string(), path.clone().into());
+    }
+    if let Some(threat) = &meta.threat_name {
+        dict.insert("ThreatName".to_string(), threat.clone().into());
+    }
+    if let Some(time) = meta.quarantine_time {
+        let date = plist::Date::from(UNIX_EPOCH + Duration::from_secs(time));
+        dict.insert("QuarantineDate".to_string(), date.into());
+    }
+    let mut info = vec![];
+    plist::Value::Dictionary(dict).to_writer_xml(&mut info)?;
+    Ok(zip_stored(
+        &[("item-0/Info.plist", &info), ("item-0/file", payload)],
+        None,
+    ))
+}
+
 fn read_ep_tag(data: &[u8], offset: usize) -> Result<(u8, usize, i64, Vec<u8>)> {
-    let code = data[offset];
+    let code = get_slice(data, offset, 1, "tag code")?[0];
     let codeval;
     let mut retdata = vec![];
     let length;
@@ -139,16 +330,16 @@ fn read_ep_tag(data: &[u8], offset: usize) -> Result<(u8, usize, i64, Vec<u8>)>
         }
         3 | 6 => {
             length = 5;
-            codeval = unpack_i32(&data[offset + 1..])? as i64;
+            codeval = unpack_i32(get_slice(data, offset + 1, 4, "tag value")?)? as i64;
         }
         4 => {
             length = 9;
-            codeval = unpack_i64(&data[offset + 1..])?;
+            codeval = unpack_i64(get_slice(data, offset + 1, 8, "tag value")?)?;
         }
         _ => {
             length = 5;
-            codeval = unpack_i32(&data[offset + 1..])? as i64;
-            retdata = data[offset + 5..offset + 5 + codeval as usize].to_vec();
+            codeval = unpack_i32(get_slice(data, offset + 1, 4, "tag length")?)? as u32 as i64;
+            retdata = get_slice(data, offset + 5, codeval as usize, "tag data")?.to_vec();
         }
     }
     Ok((code, length, codeval, retdata))
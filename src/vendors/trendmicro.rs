@@ -1,43 +1,159 @@
 use crate::{
     error::Error,
-    utils::{bytearray_xor, unpack_i16, unpack_i32},
+    metadata::{Metadata, Restored},
+    utils::{bytearray_xor, from_utf16le, get_slice, to_utf16le, unpack_i16, unpack_i32},
     Result,
 };
 
+/// VSBX encryption methods, from tag 7
+const METHOD_NONE: u16 = 0;
+const METHOD_CRC32C: u16 = 2;
+
 /// TrendMicro (Magic@0=A9 AC BD A7 which is a 'VSBX' string ^ 0xFF)
 pub fn unquarantine(data: &[u8]) -> Result<Vec<Vec<u8>>> {
+    Ok(vec![restore(data)?.data])
+}
+
+/// TrendMicro VSBX files with their tags parsed into metadata.
+///
+/// Tags as read by DeXRAY: 1 original path, 2 original name, 3 platform,
+/// 4 attributes, 6 base key and 7 encryption method. The data is either
+/// stored as is (method 0) or XORed with the CRC32C key stream keyed off
+/// tag 6 (method 2). The data of other methods, such as the newer Apex One
+/// variants, is returned as stored since their encryption is not known. The
+/// decoded variant is reported in the `variant` extra.
+pub fn restore(data: &[u8]) -> Result<Restored> {
     let mut data = bytearray_xor(data.to_owned(), 0xFF);
-    let magic = unpack_i32(&data)?;
-    let mut dataoffset = unpack_i32(&data[4..])? as usize;
-    let numtags = unpack_i16(&data[8..])?;
-    if magic != 0x58425356 {
-        // VSBX
-        return Err(Error::CannotUnQuarantineFile("trend".to_string()));
+    if !data.starts_with(b"VSBX") {
+        return Err(Error::BadMagic {
+            vendor: "TrendMicro VSBX",
+            expected: b"VSBX".to_vec(),
+            found: data.iter().take(4).copied().collect(),
+        });
     }
+    let mut dataoffset = unpack_i32(get_slice(&data, 4, 4, "data offset")?)? as usize;
+    let numtags = unpack_i16(get_slice(&data, 8, 2, "tag count")?)?;
     let mut basekey = 0x00000000;
-    let mut encmethod = 0;
+    let mut encmethod = METHOD_NONE;
+    let mut metadata = Metadata::default();
 
     if numtags > 15 {
-        return Err(Error::CannotUnQuarantineFile("trend".to_string()));
+        return Err(Error::LengthOutOfRange {
+            field: "tag count",
+            offset: 8,
+            value: numtags as u64,
+            file_len: data.len(),
+        });
     }
     dataoffset += 10;
-    let offset = 10;
+    if dataoffset > data.len() {
+        return Err(Error::LengthOutOfRange {
+            field: "data offset",
+            offset: 4,
+            value: dataoffset as u64 - 10,
+            file_len: data.len(),
+        });
+    }
+    let mut offset = 10;
     for _ in 0..numtags {
         let (code, tagdata) = read_tag(&data, offset)?;
+        offset += 3 + tagdata.len();
         match code {
+            1 => metadata.original_path = Some(from_utf16le(&tagdata)),
+            2 => {
+                metadata
+                    .extra
+                    .insert("original_name".to_string(), from_utf16le(&tagdata));
+            }
+            3 => {
+                let platform = String::from_utf8_lossy(&tagdata);
+                metadata.extra.insert(
+                    "platform".to_string(),
+                    platform.trim_end_matches('\0').to_string(),
+                );
+            }
+            4 => {
+                metadata.extra.insert(
+                    "attributes".to_string(),
+                    (unpack_i32(&tagdata)? as u32).to_string(),
+                );
+            }
             6 => {
                 basekey = unpack_i32(&tagdata)?;
             }
             7 => {
-                encmethod = unpack_i16(&tagdata)?;
+                encmethod = unpack_i16(&tagdata)? as u16;
             }
             _ => {}
         }
     }
-    if encmethod != 2 {
-        return Ok(vec![data[dataoffset..].to_vec()]);
+    let (variant, data) = match encmethod {
+        METHOD_NONE => ("OfficeScan (unencrypted)", data[dataoffset..].to_vec()),
+        METHOD_CRC32C => {
+            crc_crypt(&mut data, dataoffset, basekey);
+            (
+                "OfficeScan (CRC32C key stream)",
+                data[dataoffset..].to_vec(),
+            )
+        }
+        _ => ("unknown encryption method", data[dataoffset..].to_vec()),
+    };
+    metadata
+        .extra
+        .insert("encryption_method".to_string(), encmethod.to_string());
+    metadata
+        .extra
+        .insert("variant".to_string(), variant.to_string());
+    Ok(Restored { data, metadata })
+}
+
+/// Wrap a payload into a TrendMicro VSBX file (OfficeScan, encryption method 2)
+pub fn quarantine(payload: &[u8], meta: &Metadata) -> Result<Vec<u8>> {
+    quarantine_with_method(payload, meta, METHOD_CRC32C)
+}
+
+/// Wrap a payload into a TrendMicro VSBX file with the given encryption method
+pub fn quarantine_with_method(payload: &[u8], meta: &Metadata, method: u16) -> Result<Vec<u8>> {
+    let basekey: i32 = 0x2A;
+    let mut tags = vec![];
+    let mut numtags = 0u16;
+    let mut tag = |code: u8, data: &[u8]| {
+        tags.push(code);
+        tags.extend((data.len() as u16).to_le_bytes());
+        tags.extend(data);
+        numtags += 1;
+    };
+    if let Some(path) = &meta.original_path {
+        tag(1, &to_utf16le(&format!("{}\0", path)));
+    }
+    tag(6, &basekey.to_le_bytes());
+    tag(7, &method.to_le_bytes());
+
+    let mut data = vec![];
+    data.extend(0x58425356u32.to_le_bytes());
+    data.extend((tags.len() as u32).to_le_bytes());
+    data.extend(numtags.to_le_bytes());
+    data.extend(tags);
+    let dataoffset = data.len();
+    match method {
+        METHOD_NONE => data.extend(payload),
+        METHOD_CRC32C => {
+            data.extend(payload);
+            crc_crypt(&mut data, dataoffset, basekey);
+        }
+        method => {
+            return Err(Error::UnsupportedVersion {
+                vendor: "TrendMicro VSBX",
+                version: method as u32,
+            })
+        }
     }
-    let mut bytesleft = data.len() - dataoffset as usize;
+    Ok(bytearray_xor(data, 0xFF))
+}
+
+/// XOR the data from `dataoffset` on with the CRC32C key stream (encryption method 2)
+fn crc_crypt(data: &mut [u8], dataoffset: usize, basekey: i32) {
+    let mut bytesleft = data.len() - dataoffset;
     let mut unaligned = dataoffset % 4;
     let mut firstiter = true;
     let mut curoffset = dataoffset;
@@ -47,7 +163,7 @@ pub fn unquarantine(data: &[u8]) -> Result<Vec<Vec<u8>>> {
             off = curoffset - unaligned;
             firstiter = false;
         }
-        let keyval = basekey + off as i32;
+        let keyval = basekey.wrapping_add(off as i32);
         let cc = crc::Crc::<u32>::new(&crc::CRC_32_ISCSI);
         let crcbuf = cc.checksum(&keyval.to_le_bytes()[..]).to_le_bytes();
         for i in unaligned..4 {
@@ -60,11 +176,13 @@ pub fn unquarantine(data: &[u8]) -> Result<Vec<Vec<u8>>> {
         }
         unaligned = 0;
     }
-    Ok(vec![data[dataoffset..].to_vec()])
 }
 
 fn read_tag(data: &[u8], offset: usize) -> Result<(u8, Vec<u8>)> {
-    let code = data[offset];
-    let length = unpack_i16(&data[offset + 1..])? as usize;
-    Ok((code, data[offset + 3..offset + 3 + length].to_vec()))
+    let code = get_slice(data, offset, 1, "tag code")?[0];
+    let length = unpack_i16(get_slice(data, offset + 1, 2, "tag length")?)? as u16 as usize;
+    Ok((
+        code,
+        get_slice(data, offset + 3, length, "tag data")?.to_vec(),
+    ))
 }
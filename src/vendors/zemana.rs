@@ -1,10 +1,15 @@
-use crate::{utils::rc4_decrypt, Result};
+use crate::{metadata::Metadata, utils::rc4_decrypt, Result};
 
 /// Zemana <hash> files+quarantine.db
 pub fn unquarantine(data: &[u8]) -> Result<Vec<Vec<u8>>> {
     Ok(vec![rc4_decrypt(&mut ksa(), &mut data.to_vec())])
 }
 
+/// Wrap a payload into a Zemana file
+pub fn quarantine(payload: &[u8], _meta: &Metadata) -> Result<Vec<u8>> {
+    Ok(rc4_decrypt(&mut ksa(), &mut payload.to_vec()))
+}
+
 fn ksa() -> Vec<u8> {
     let key = b"A8147B3ABF8533AB27FA9551B1FAA385";
     let mut sbox: Vec<u8> = (0..=255).collect();